@@ -0,0 +1,11 @@
+use std::env;
+use std::fs;
+
+// Plays Day 25 from stdin. Takes the path to the puzzle input, defaulting to the one used by the tests.
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "input/2019/day25.txt".to_owned());
+    let input = fs::read_to_string(path).expect("Something went wrong reading the file");
+    advent_of_code_2019::play_day25(&input);
+}
@@ -41,7 +41,7 @@
     What is the Manhattan distance from the central port to the closest intersection?
 */
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 struct Point {
@@ -49,58 +49,212 @@ struct Point {
     y: i32,
 }
 
-fn get_points_from_path(path: Vec<&str>) -> HashSet<Point> {
-    let mut points: HashSet<Point> = HashSet::new();
-    let mut current_point = Point { x: 0, y: 0 };
+const ORIGIN: Point = Point { x: 0, y: 0 };
+
+fn manhattan_distance(p: Point) -> u32 {
+    let distance = p.x.abs() + p.y.abs();
+    distance as u32
+}
+
+// A straight run of one wire. `steps` is how far along the wire `start` is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Segment {
+    wire: usize,
+    start: Point,
+    end: Point,
+    steps: u32,
+}
+
+impl Segment {
+    fn is_horizontal(&self) -> bool {
+        self.start.y == self.end.y
+    }
+
+    fn min(&self) -> Point {
+        Point {
+            x: self.start.x.min(self.end.x),
+            y: self.start.y.min(self.end.y),
+        }
+    }
+
+    fn max(&self) -> Point {
+        Point {
+            x: self.start.x.max(self.end.x),
+            y: self.start.y.max(self.end.y),
+        }
+    }
+
+    fn steps_to(&self, p: Point) -> u32 {
+        self.steps + ((p.x - self.start.x).abs() + (p.y - self.start.y).abs()) as u32
+    }
+}
+
+fn get_segments_from_path(path: Vec<&str>, wire: usize) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current_point = ORIGIN;
+    let mut steps = 0;
 
     for segment in path {
         let direction = segment.as_bytes()[0];
-        let count = segment[1..].parse::<u32>().unwrap();
-        // println!("Segment {}, {}", direction, count);
-
-        for _ in 0..count {
-            match direction as char {
-                'R' => current_point.x += 1,
-                'L' => current_point.x -= 1,
-                'U' => current_point.y += 1,
-                'D' => current_point.y -= 1,
-                _ => panic!("Bad format"),
-            }
+        let count = segment[1..].parse::<i32>().unwrap();
 
-            points.insert(current_point);
+        let start = current_point;
+        match direction as char {
+            'R' => current_point.x += count,
+            'L' => current_point.x -= count,
+            'U' => current_point.y += count,
+            'D' => current_point.y -= count,
+            _ => panic!("Bad format"),
         }
+
+        segments.push(Segment {
+            wire,
+            start,
+            end: current_point,
+            steps,
+        });
+        steps += count as u32;
     }
 
-    points
+    segments
 }
 
-fn intersection(points1: &HashSet<Point>, points2: &HashSet<Point>) -> Vec<Point> {
-    let intersection: Vec<Point> = points1
-        .iter()
-        .filter(|&&p1| points2.contains(&p1))
-        .copied()
-        .collect();
-    // println!("Intersection = {:?}", intersection);
-    intersection
+#[derive(Clone, Debug, PartialEq)]
+struct Intersection {
+    point: Point,
+    // The wires that meet here, with the fewest steps each takes to reach it
+    wires: Vec<(usize, u32)>,
+    distance: u32,
+    steps: u32,
 }
 
-fn manhattan_distance(p: Point) -> u32 {
-    let distance = p.x.abs() + p.y.abs();
-    distance as u32
+struct Circuit {
+    wires: Vec<Vec<Segment>>,
 }
 
-fn best_intersection(path1: Vec<&str>, path2: Vec<&str>) -> u32 {
-    let path1_points = get_points_from_path(path1);
-    let path2_points = get_points_from_path(path2);
+impl Circuit {
+    fn new(paths: Vec<Vec<&str>>) -> Circuit {
+        let wires = paths
+            .into_iter()
+            .enumerate()
+            .map(|(wire, path)| get_segments_from_path(path, wire))
+            .collect();
+        Circuit { wires }
+    }
+
+    fn from_string(input: &str) -> Circuit {
+        Circuit::new(
+            input
+                .lines()
+                .filter(|line| line.trim().is_empty() == false)
+                .map(|line| line.trim().split(',').collect())
+                .collect(),
+        )
+    }
+
+    // Every point other than the origin where two or more wires meet, closest first
+    fn intersections(&self) -> Vec<Intersection> {
+        let segments: Vec<&Segment> = self.wires.iter().flatten().collect();
+        let mut visits: HashMap<Point, HashMap<usize, u32>> = HashMap::new();
+        let mut record = |a: &Segment, b: &Segment, p: Point| {
+            if a.wire == b.wire || p == ORIGIN {
+                return;
+            }
+            for s in [a, b].iter() {
+                let steps = s.steps_to(p);
+                let best = visits.entry(p).or_default().entry(s.wire).or_insert(steps);
+                *best = (*best).min(steps);
+            }
+        };
+
+        // Sweep from left to right. Horizontal segments are active between their ends and each
+        // vertical segment crosses the active ones within its span.
+        let mut events: Vec<(i32, u8, usize)> = Vec::new();
+        for (idx, s) in segments.iter().enumerate() {
+            if s.is_horizontal() == true {
+                events.push((s.min().x, 0, idx));
+                events.push((s.max().x, 2, idx));
+            } else {
+                events.push((s.start.x, 1, idx));
+            }
+        }
+        events.sort();
+
+        let mut active: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for (x, kind, idx) in events {
+            let s = segments[idx];
+            match kind {
+                0 => active.entry(s.start.y).or_default().push(idx),
+                1 => {
+                    for (&y, horizontals) in active.range(s.min().y..=s.max().y) {
+                        for &h in horizontals {
+                            record(segments[h], s, Point { x, y });
+                        }
+                    }
+                }
+                _ => active.get_mut(&s.start.y).unwrap().retain(|&h| h != idx),
+            }
+        }
+
+        // Segments lying along the same line meet wherever they overlap
+        let mut lines: HashMap<(bool, i32), Vec<&Segment>> = HashMap::new();
+        for s in segments.iter() {
+            let line = match s.is_horizontal() {
+                true => (true, s.start.y),
+                false => (false, s.start.x),
+            };
+            lines.entry(line).or_default().push(s);
+        }
+        for ((horizontal, _), mut line) in lines {
+            let span = |s: &Segment| match horizontal {
+                true => (s.min().x, s.max().x),
+                false => (s.min().y, s.max().y),
+            };
+            line.sort_by_key(|s| span(s));
+            for (i, a) in line.iter().enumerate() {
+                for b in line[i + 1..].iter() {
+                    let (_, a_max) = span(a);
+                    let (b_min, b_max) = span(b);
+                    if b_min > a_max {
+                        break;
+                    }
+                    for v in b_min..=a_max.min(b_max) {
+                        let p = match horizontal {
+                            true => Point { x: v, y: a.start.y },
+                            false => Point { x: a.start.x, y: v },
+                        };
+                        record(a, b, p);
+                    }
+                }
+            }
+        }
 
-    let intersect_points = intersection(&path1_points, &path2_points);
+        let mut intersections: Vec<Intersection> = visits
+            .into_iter()
+            .map(|(point, wires)| {
+                let mut wires: Vec<(usize, u32)> = wires.into_iter().collect();
+                wires.sort();
+                Intersection {
+                    point,
+                    distance: manhattan_distance(point),
+                    steps: wires.iter().map(|&(_, steps)| steps).sum(),
+                    wires,
+                }
+            })
+            .collect();
+        intersections.sort_by_key(|i| (i.distance, i.steps, i.point.x, i.point.y));
+        intersections
+    }
+}
 
-    let closest_distance = intersect_points
+fn best_intersection(path1: Vec<&str>, path2: Vec<&str>) -> u32 {
+    let circuit = Circuit::new(vec![path1, path2]);
+    circuit
+        .intersections()
         .iter()
-        .map(|&p| manhattan_distance(p))
+        .map(|i| i.distance)
         .min()
-        .unwrap();
-    closest_distance
+        .unwrap()
 }
 
 #[aoc(day3, part1)]
@@ -140,4 +294,12 @@ mod test {
             .collect::<Vec<&str>>();
         assert_eq!(best_intersection(path3_a, path3_b), 135);
     }
+
+    #[test]
+    fn test_intersections_along_a_wire() {
+        // The second wire ends on the first, lying along it
+        let circuit = Circuit::from_string("U2,R3\nR1,U3,L1,D1");
+        let distances: Vec<u32> = circuit.intersections().iter().map(|i| i.distance).collect();
+        assert_eq!(distances, vec![2, 3]);
+    }
 }
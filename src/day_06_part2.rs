@@ -46,117 +46,226 @@
     What is the minimum number of orbital transfers required to move from the object YOU are orbiting to the object SAN is orbiting? (Between the objects they are orbiting - not between YOU and SAN.)
 */
 
-#[derive(Clone)]
-struct SpaceObject {
-    name: String,
-    parent_name: String,
-    parent_idx: Option<usize>, // Index into vector; proxy for linked graph. Option because one node points to COM which is then None
-    orbit_count: u32,
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+enum OrbitError {
+    BadLine(usize, String),
+    DuplicateBody(String),
+    Orphan(String),
+    Cycle(String),
+    UnknownBody(String),
+    NotInOrbit(String),
 }
 
-fn build_graph(input: &str) -> Vec<SpaceObject> {
-    // Build vector of space objects
-    let mut graph: Vec<SpaceObject> = Vec::new();
-    for line in input.lines() {
-        // println!("line: {}", line);
-        let items: Vec<&str> = line.split(')').collect();
-        let obj = SpaceObject {
-            name: items[1].to_string(),
-            parent_name: items[0].to_string(),
-            parent_idx: None,
-            orbit_count: 0, // 0 is invalid: no element can have this (except COM, which isn't instantiated)
-        };
-        graph.push(obj);
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrbitError::BadLine(line, text) => write!(f, "Bad orbit {:?} on line {}", text, line),
+            OrbitError::DuplicateBody(name) => write!(f, "{} orbits more than one body", name),
+            OrbitError::Orphan(name) => write!(f, "{} does not orbit anything", name),
+            OrbitError::Cycle(name) => write!(f, "{} is part of an orbit cycle", name),
+            OrbitError::UnknownBody(name) => write!(f, "Unknown body {}", name),
+            OrbitError::NotInOrbit(name) => write!(f, "{} is not in orbit", name),
+        }
     }
+}
 
-    // Traverse the vector and update parent index
-    for i in 0..graph.len() {
-        // Can't directly iterate over objects because the borrow checker complains
-        if graph[i].parent_name != "COM" {
-            let parent_idx = graph
-                .iter()
-                .position(|obj| obj.name == graph[i].parent_name);
-            graph[i].parent_idx = parent_idx;
+// The bodies and who orbits whom, as a tree hanging off the root
+struct OrbitMap {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    parent: Vec<Option<usize>>,
+    depth: Vec<usize>,
+    // ancestors[k][i] is the body 2^k steps towards the root from i, stopping at the root
+    ancestors: Vec<Vec<usize>>,
+    root: usize,
+}
+
+impl OrbitMap {
+    fn from_string(input: &str, root: &str) -> Result<OrbitMap, OrbitError> {
+        let mut names: Vec<String> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut parent: Vec<Option<usize>> = Vec::new();
+        let mut body_idx =
+            |name: &str, names: &mut Vec<String>, parent: &mut Vec<Option<usize>>| {
+                *index.entry(name.to_string()).or_insert_with(|| {
+                    names.push(name.to_string());
+                    parent.push(None);
+                    names.len() - 1
+                })
+            };
+
+        for (line_num, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() == true {
+                continue;
+            }
+            let items: Vec<&str> = line.split(')').collect();
+            if items.len() != 2 || items.iter().any(|item| item.is_empty()) {
+                return Err(OrbitError::BadLine(line_num + 1, line.to_string()));
+            }
+            let center = body_idx(items[0], &mut names, &mut parent);
+            let body = body_idx(items[1], &mut names, &mut parent);
+            if parent[body].is_some() {
+                return Err(OrbitError::DuplicateBody(items[1].to_string()));
+            }
+            parent[body] = Some(center);
         }
-    }
 
-    // Traverse the vector and update orbit count
-    for i in 0..graph.len() {
-        let mut count = 0;
-        let mut curr_idx = i;
-
-        loop {
-            if graph[curr_idx].orbit_count != 0 {
-                // Found a node that knows its orbit count -- use it to short circuit the calculation.
-                count += graph[curr_idx].orbit_count;
-                break;
-            } else {
-                match graph[curr_idx].parent_idx {
-                    Some(p_idx) => {
-                        // Node doesn't know its orbit count, follow its parent
-                        curr_idx = p_idx;
-                        count += 1;
-                    }
-                    None => {
-                        // Node points to COM -- its orbit count is effectively 1
-                        count += 1;
-                        break;
-                    }
+        // Anything the root can't reach either circles back on itself or hangs off some other top body
+        let stranded = |mut body: usize| {
+            let mut seen = vec![false; names.len()];
+            loop {
+                if seen[body] == true {
+                    return OrbitError::Cycle(names[body].clone());
+                }
+                seen[body] = true;
+                match parent[body] {
+                    Some(center) => body = center,
+                    None => return OrbitError::Orphan(names[body].clone()),
                 }
             }
+        };
+
+        let root = match index.get(root) {
+            Some(&root) => root,
+            None => return Err(OrbitError::UnknownBody(root.to_string())),
+        };
+        if parent[root].is_some() {
+            return Err(stranded(root));
         }
 
-        graph[i].orbit_count = count;
+        // Depths from the root, walking down through each body's satellites
+        let mut satellites: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        for (body, center) in parent.iter().enumerate() {
+            if let Some(center) = center {
+                satellites[*center].push(body);
+            }
+        }
+        let mut depth: Vec<Option<usize>> = vec![None; names.len()];
+        depth[root] = Some(0);
+        let mut stack = vec![root];
+        while let Some(body) = stack.pop() {
+            for &satellite in satellites[body].iter() {
+                depth[satellite] = Some(depth[body].unwrap() + 1);
+                stack.push(satellite);
+            }
+        }
+
+        if let Some(lost) = depth.iter().position(|d| d.is_none()) {
+            return Err(stranded(lost));
+        }
+        let depth: Vec<usize> = depth.into_iter().map(|d| d.unwrap()).collect();
+
+        let mut ancestors = vec![parent
+            .iter()
+            .map(|p| p.unwrap_or(root))
+            .collect::<Vec<usize>>()];
+        let max_depth = depth.iter().max().copied().unwrap_or(0);
+        while 1 << ancestors.len() <= max_depth {
+            let last = ancestors.last().unwrap();
+            let next = last.iter().map(|&a| last[a]).collect();
+            ancestors.push(next);
+        }
+
+        Ok(OrbitMap {
+            names,
+            index,
+            parent,
+            depth,
+            ancestors,
+            root,
+        })
     }
 
-    // graph.iter().for_each(|o| println!("{}", o.orbit_count));
-    graph
-}
+    fn get(&self, name: &str) -> Result<usize, OrbitError> {
+        self.index
+            .get(name)
+            .copied()
+            .ok_or_else(|| OrbitError::UnknownBody(name.to_string()))
+    }
 
-fn find_node_in_graph(graph: &[SpaceObject], node_name: &str) -> usize {
-    let node_idx = graph
-        .iter()
-        .position(|obj| obj.name == node_name)
-        .expect("Failed to find node!");
-    node_idx
-}
+    // Direct and indirect orbits of every body
+    fn total_orbits(&self) -> usize {
+        self.depth.iter().sum()
+    }
 
-fn get_path_to_root(graph: &[SpaceObject], from_name: &str) -> Vec<SpaceObject> {
-    let mut path = Vec::new();
+    // Steps `count` bodies towards the root
+    fn ancestor(&self, mut body: usize, count: usize) -> usize {
+        for (k, level) in self.ancestors.iter().enumerate() {
+            if count & (1 << k) != 0 {
+                body = level[body];
+            }
+        }
+        body
+    }
 
-    let mut idx = find_node_in_graph(graph, from_name);
-    while let Some(next_idx) = graph[idx].parent_idx {
-        path.push(graph[next_idx].clone());
-        idx = next_idx;
+    fn lca_idx(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = match self.depth[a] >= self.depth[b] {
+            true => (self.ancestor(a, self.depth[a] - self.depth[b]), b),
+            false => (a, self.ancestor(b, self.depth[b] - self.depth[a])),
+        };
+        if a == b {
+            return a;
+        }
+        for level in self.ancestors.iter().rev() {
+            if level[a] != level[b] {
+                a = level[a];
+                b = level[b];
+            }
+        }
+        self.ancestors[0][a]
     }
 
-    path
-}
+    // The deepest body that both bodies orbit, directly or not
+    fn lowest_common_ancestor(&self, a: &str, b: &str) -> Result<&str, OrbitError> {
+        let lca = self.lca_idx(self.get(a)?, self.get(b)?);
+        Ok(&self.names[lca])
+    }
+
+    fn distance(&self, a: usize, b: usize) -> usize {
+        self.depth[a] + self.depth[b] - 2 * self.depth[self.lca_idx(a, b)]
+    }
 
-// Basic strategy is to trace a path from each given node to the root and find the intersection point.
-// Then, sum the hops from each node to the intersection point.
-fn count_orbital_transfers(graph: &[SpaceObject], src_name: &str, dst_name: &str) -> u32 {
-    let src_path = get_path_to_root(graph, src_name);
-    let dst_path = get_path_to_root(graph, dst_name);
-
-    // Find intersection
-    let src_to_intersection = src_path
-        .iter()
-        .position(|src_obj| dst_path.iter().any(|dst_obj| src_obj.name == dst_obj.name))
-        .unwrap();
-    let intersection_to_dst = dst_path
-        .iter()
-        .position(|dst_obj| src_path.iter().any(|src_obj| src_obj.name == dst_obj.name))
-        .unwrap();
-
-    let total_transfers = src_to_intersection + intersection_to_dst;
-    total_transfers as u32
+    // Orbital transfers needed to move from the body `src` orbits to the body `dst` orbits
+    fn transfers(&self, src: &str, dst: &str) -> Result<usize, OrbitError> {
+        let center = |name: &str| {
+            self.parent[self.get(name)?].ok_or_else(|| OrbitError::NotInOrbit(name.to_string()))
+        };
+        Ok(self.distance(center(src)?, center(dst)?))
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph orbits {\n");
+        dot.push_str(&format!(
+            "    \"{}\" [shape=doublecircle];\n",
+            self.names[self.root]
+        ));
+        for (body, center) in self.parent.iter().enumerate() {
+            if let Some(center) = center {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    self.names[*center], self.names[body]
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
 }
 
 #[aoc(day6, part2)]
 pub fn solve(input: &str) -> u32 {
-    let graph = build_graph(&input);
-    let transfers = count_orbital_transfers(&graph, "YOU", "SAN");
+    let orbits = match OrbitMap::from_string(input, "COM") {
+        Ok(orbits) => orbits,
+        Err(e) => panic!("{}", e),
+    };
+    let transfers = match orbits.transfers("YOU", "SAN") {
+        Ok(transfers) => transfers as u32,
+        Err(e) => panic!("{}", e),
+    };
     println!("Transfers: {}", transfers);
     transfers
 }
@@ -165,9 +274,7 @@ pub fn solve(input: &str) -> u32 {
 mod test {
     use super::*;
 
-    #[test]
-    fn test_count_orbital_transfers() {
-        let input = "\
+    const EXAMPLE: &str = "\
 COM)B
 B)C
 C)D
@@ -181,7 +288,66 @@ J)K
 K)L
 K)YOU
 I)SAN";
-        let graph = build_graph(&input);
-        assert_eq!(count_orbital_transfers(&graph, "YOU", "SAN"), 4);
+
+    #[test]
+    fn test_count_orbital_transfers() {
+        let orbits = OrbitMap::from_string(EXAMPLE, "COM").unwrap();
+        assert_eq!(orbits.transfers("YOU", "SAN"), Ok(4));
+    }
+
+    #[test]
+    fn test_orbit_map() {
+        let orbits = OrbitMap::from_string(EXAMPLE, "COM").unwrap();
+        assert_eq!(orbits.total_orbits(), 54);
+        assert_eq!(orbits.lowest_common_ancestor("YOU", "SAN"), Ok("D"));
+        assert_eq!(orbits.lowest_common_ancestor("L", "F"), Ok("E"));
+        assert_eq!(orbits.lowest_common_ancestor("H", "H"), Ok("H"));
+        assert_eq!(orbits.lowest_common_ancestor("COM", "L"), Ok("COM"));
+        assert_eq!(orbits.transfers("L", "H"), Ok(6));
+        assert_eq!(
+            orbits.transfers("YOU", "X"),
+            Err(OrbitError::UnknownBody("X".to_string()))
+        );
+        assert_eq!(
+            orbits.transfers("COM", "SAN"),
+            Err(OrbitError::NotInOrbit("COM".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_orbit_map_errors() {
+        let errors = [
+            ("COM)B\nB-C", OrbitError::BadLine(2, "B-C".to_string())),
+            (
+                "COM)B\nB)C\nCOM)C",
+                OrbitError::DuplicateBody("C".to_string()),
+            ),
+            ("COM)B\nX)C", OrbitError::Orphan("X".to_string())),
+            ("COM)B\nC)D\nD)C", OrbitError::Cycle("C".to_string())),
+            ("B)COM\nCOM)B", OrbitError::Cycle("COM".to_string())),
+            ("A)B", OrbitError::UnknownBody("COM".to_string())),
+        ];
+        for (input, error) in errors.iter() {
+            assert_eq!(
+                OrbitMap::from_string(input, "COM").err().as_ref(),
+                Some(error)
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let orbits = OrbitMap::from_string("COM)B\nB)C\nB)D", "COM").unwrap();
+        assert_eq!(
+            orbits.to_dot(),
+            "\
+digraph orbits {
+    \"COM\" [shape=doublecircle];
+    \"COM\" -> \"B\";
+    \"B\" -> \"C\";
+    \"B\" -> \"D\";
+}
+"
+        );
     }
 }
@@ -32,6 +32,201 @@
 
 use std::fmt;
 
+// The capital-letter font the puzzles draw with. Each glyph sits in a cell 6 pixels tall and 5 wide,
+// which leaves a blank column after every letter except the wide Y. The narrow I is only 3 wide.
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_WIDTH: usize = 5;
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq)]
+struct UnknownGlyph {
+    index: usize,
+    column: usize,
+    rows: Vec<String>,
+}
+
+impl fmt::Display for UnknownGlyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Unknown glyph {} at column {}:",
+            self.index + 1,
+            self.column
+        )?;
+        for row in self.rows.iter() {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+// Cuts the lit pixels into glyphs at blank columns, at most a cell wide, and looks each one up in the font
+fn read_glyphs(pixels: &[Vec<bool>]) -> Result<String, Vec<UnknownGlyph>> {
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |row: usize, col: usize| pixels[row].get(col) == Some(&true);
+    let blank = |col: usize| (0..pixels.len()).all(|row| lit(row, col) == false);
+
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    let mut col = 0;
+    while let Some(start) = (col..width).find(|&c| blank(c) == false) {
+        let limit = (start + GLYPH_WIDTH).min(width);
+        let end = (start..limit).find(|&c| blank(c) == true).unwrap_or(limit);
+        let rows: Vec<String> = (0..pixels.len())
+            .map(|row| {
+                (start..end)
+                    .map(|c| if lit(row, c) == true { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        let glyph = FONT
+            .iter()
+            .find(|(_, font_rows)| font_rows.iter().eq(rows.iter()))
+            .map(|&(letter, _)| letter);
+        match glyph {
+            Some(letter) => text.push(letter),
+            None => {
+                unknown.push(UnknownGlyph {
+                    index: text.len(),
+                    column: start,
+                    rows,
+                });
+                text.push('?');
+            }
+        }
+        col = end;
+    }
+
+    if unknown.is_empty() == true {
+        Ok(text)
+    } else {
+        Err(unknown)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SifError {
+    EmptySize,
+    BadDigit(usize, char),
+    BadLength(usize, usize),
+    SizeMismatch(usize),
+    BadHeader(String),
+    BadValue(usize, String),
+    UnknownColor(usize),
+}
+
+impl fmt::Display for SifError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SifError::EmptySize => write!(f, "Image has no pixels"),
+            SifError::BadDigit(pos, c) => write!(f, "Bad digit {:?} at position {}", c, pos),
+            SifError::BadLength(len, layer_size) => write!(
+                f,
+                "Length {} is not a multiple of the layer size {}",
+                len, layer_size
+            ),
+            SifError::SizeMismatch(layer) => {
+                write!(f, "Layer {} does not match the image size", layer)
+            }
+            SifError::BadHeader(header) => write!(f, "Bad image header: {}", header),
+            SifError::BadValue(pixel, value) => {
+                write!(f, "Bad value {:?} for pixel {}", value, pixel)
+            }
+            SifError::UnknownColor(pixel) => write!(f, "Pixel {} is not in the palette", pixel),
+        }
+    }
+}
+
+type Rgb = (u8, u8, u8);
+
+// Maps each digit to a colour. Digits without a colour are transparent and show the background.
+struct Palette {
+    colors: Vec<Option<Rgb>>,
+    background: Rgb,
+}
+
+impl Palette {
+    fn new(background: Rgb) -> Self {
+        Self {
+            colors: vec![None; 10],
+            background,
+        }
+    }
+
+    // The puzzle's palette: 0 is black, 1 is white and 2 is transparent. The other digits are shades of grey.
+    fn space() -> Self {
+        let mut palette = Self::new((0, 0, 0));
+        palette.set(0, Some((0, 0, 0)));
+        palette.set(1, Some((255, 255, 255)));
+        for digit in 3..10 {
+            let shade = 32 * (digit - 2) as u8;
+            palette.set(digit, Some((shade, shade, shade)));
+        }
+        palette
+    }
+
+    fn set(&mut self, digit: u32, color: Option<Rgb>) {
+        self.colors[digit as usize] = color;
+    }
+
+    fn color(&self, digit: u32) -> Option<Rgb> {
+        self.colors.get(digit as usize).cloned().flatten()
+    }
+
+    fn is_transparent(&self, digit: u32) -> bool {
+        self.color(digit).is_none()
+    }
+
+    fn first_transparent(&self) -> Option<u32> {
+        (0..self.colors.len() as u32).find(|&d| self.is_transparent(d))
+    }
+
+    fn rgb(&self, digit: u32) -> Rgb {
+        self.color(digit).unwrap_or(self.background)
+    }
+
+    // The first digit drawn in a matching colour. The background maps back to the first transparent digit.
+    fn digit<F: Fn(Rgb) -> bool>(&self, matches: F) -> Option<u32> {
+        let opaque =
+            (0..self.colors.len() as u32).find(|&d| self.color(d).map(&matches) == Some(true));
+        opaque.or_else(|| match matches(self.background) {
+            true => self.first_transparent(),
+            false => None,
+        })
+    }
+}
+
+fn gray((r, g, b): Rgb) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+}
+
+// The whitespace separated values of a plain PGM (P2) or PPM (P3), with comments removed
+fn pnm_tokens(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap())
+        .flat_map(|line| line.split_whitespace())
+        .collect()
+}
+
 pub struct Layer {
     data: Vec<u32>,
     width: usize,
@@ -61,6 +256,116 @@ impl Layer {
         let count2 = self.count_digits(digit2);
         count1 * count2
     }
+
+    fn histogram(&self) -> [usize; 10] {
+        let mut histogram = [0; 10];
+        for &d in self.data.iter() {
+            histogram[d as usize] += 1;
+        }
+        histogram
+    }
+
+    fn encode(&self) -> String {
+        self.data
+            .iter()
+            .map(|&d| std::char::from_digit(d, 10).unwrap())
+            .collect()
+    }
+
+    // Plain PGM, using the grey level of each palette colour
+    fn to_pgm(&self, palette: &Palette) -> String {
+        let mut image = format!("P2\n{} {}\n255\n", self.width, self.height);
+        for row in self.data.chunks(self.width) {
+            let values: Vec<String> = row
+                .iter()
+                .map(|&d| gray(palette.rgb(d)).to_string())
+                .collect();
+            image.push_str(&values.join(" "));
+            image.push('\n');
+        }
+        image
+    }
+
+    // Plain PPM
+    fn to_ppm(&self, palette: &Palette) -> String {
+        let mut image = format!("P3\n{} {}\n255\n", self.width, self.height);
+        for row in self.data.chunks(self.width) {
+            let values: Vec<String> = row
+                .iter()
+                .map(|&d| {
+                    let (r, g, b) = palette.rgb(d);
+                    format!("{} {} {}", r, g, b)
+                })
+                .collect();
+            image.push_str(&values.join("  "));
+            image.push('\n');
+        }
+        image
+    }
+
+    // Reads a plain PGM or PPM back into digits. Every pixel must be a palette colour.
+    fn from_pnm(text: &str, palette: &Palette) -> Result<Layer, SifError> {
+        let tokens = pnm_tokens(text);
+        let channels = match tokens.first() {
+            Some(&"P2") => 1,
+            Some(&"P3") => 3,
+            _ => {
+                return Err(SifError::BadHeader(
+                    text.lines().next().unwrap_or("").to_string(),
+                ))
+            }
+        };
+        let header: Vec<usize> = tokens
+            .iter()
+            .skip(1)
+            .take(3)
+            .map(|t| t.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| SifError::BadHeader(tokens[..tokens.len().min(4)].join(" ")))?;
+        if header.len() < 3 {
+            return Err(SifError::BadHeader(tokens.join(" ")));
+        }
+        let (width, height, max) = (header[0], header[1], header[2]);
+        if width * height == 0 {
+            return Err(SifError::EmptySize);
+        }
+        if max != 255 {
+            return Err(SifError::BadHeader(format!("max value {}", max)));
+        }
+
+        let values = &tokens[4..];
+        if values.len() != width * height * channels {
+            return Err(SifError::BadLength(values.len(), width * height * channels));
+        }
+        let mut data = Vec::new();
+        for (pixel, chunk) in values.chunks(channels).enumerate() {
+            let channel_values: Vec<u8> = chunk
+                .iter()
+                .map(|v| v.parse::<u8>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| SifError::BadValue(pixel, chunk.join(" ")))?;
+            let digit = match channels {
+                1 => palette.digit(|rgb| gray(rgb) == channel_values[0]),
+                _ => palette
+                    .digit(|rgb| rgb == (channel_values[0], channel_values[1], channel_values[2])),
+            };
+            match digit {
+                Some(d) => data.push(d),
+                None => return Err(SifError::UnknownColor(pixel)),
+            }
+        }
+        Ok(Layer::from_slice(&data, width, height))
+    }
+
+    // Reads the white (1) pixels as text
+    fn text(&self) -> Result<String, Vec<UnknownGlyph>> {
+        let pixels: Vec<Vec<bool>> = self
+            .data
+            .chunks(self.width)
+            .map(|row| row.iter().map(|&p| p == 1).collect())
+            .collect();
+        read_glyphs(&pixels)
+    }
 }
 
 impl fmt::Display for Layer {
@@ -77,6 +382,25 @@ impl fmt::Display for Layer {
     }
 }
 
+#[derive(Debug, PartialEq)]
+struct LayerStats {
+    histogram: [usize; 10],
+    // Pixels that are not transparent
+    opaque: usize,
+    // Pixels of the final image that come from this layer
+    visible: usize,
+}
+
+impl LayerStats {
+    fn most_common(&self) -> u32 {
+        (0..10).rev().max_by_key(|&d| self.histogram[d]).unwrap() as u32
+    }
+
+    fn coverage(&self) -> f64 {
+        self.opaque as f64 / self.histogram.iter().sum::<usize>() as f64
+    }
+}
+
 struct Image {
     layers: Vec<Layer>,
     width: usize,
@@ -97,6 +421,46 @@ impl Image {
         }
     }
 
+    // Parses the Space Image Format, checking every digit and that the layers are all complete
+    fn parse(input: &str, width: usize, height: usize) -> Result<Image, SifError> {
+        let layer_size = width * height;
+        if layer_size == 0 {
+            return Err(SifError::EmptySize);
+        }
+        let input = input.trim();
+        let digits: Vec<u32> = input
+            .chars()
+            .enumerate()
+            .map(|(pos, c)| c.to_digit(10).ok_or(SifError::BadDigit(pos, c)))
+            .collect::<Result<_, _>>()?;
+        if digits.is_empty() || digits.len().is_multiple_of(layer_size) == false {
+            return Err(SifError::BadLength(digits.len(), layer_size));
+        }
+        Ok(Image::from_slice(&digits, width, height))
+    }
+
+    fn from_layers(layers: Vec<Layer>) -> Result<Image, SifError> {
+        let (width, height) = match layers.first() {
+            Some(layer) => (layer.width, layer.height),
+            None => return Err(SifError::EmptySize),
+        };
+        if let Some(idx) = layers
+            .iter()
+            .position(|l| l.width != width || l.height != height || l.data.len() != width * height)
+        {
+            return Err(SifError::SizeMismatch(idx));
+        }
+        Ok(Image {
+            layers,
+            width,
+            height,
+        })
+    }
+
+    fn encode(&self) -> String {
+        self.layers.iter().map(|layer| layer.encode()).collect()
+    }
+
     fn display(&self, layer_num: u32) {
         println!("{}", self.layers[layer_num as usize]);
     }
@@ -106,15 +470,21 @@ impl Image {
     }
 
     fn stack_layers(&self) -> Layer {
+        self.flatten(&Palette::space())
+    }
+
+    // The top visible pixel in each position
+    fn flatten(&self, palette: &Palette) -> Layer {
+        let transparent = palette.first_transparent().unwrap_or(2);
         let mut pixel_vec = Vec::new();
-        for row in 0..self.layers[0].height {
-            for col in 0..self.layers[0].width {
+        for row in 0..self.height {
+            for col in 0..self.width {
                 let pixel = self
                     .layers
                     .iter()
                     .map(|layer| layer.pixel(row, col)) // Get the pixel value at this layer
-                    .find(|&pixel| pixel != 2) // Find the first non-transparent pixel
-                    .unwrap_or(2); // If all were transparent, default to the transparent value
+                    .find(|&pixel| palette.is_transparent(pixel) == false) // Find the first non-transparent pixel
+                    .unwrap_or(transparent); // If all were transparent, default to the transparent value
                 pixel_vec.push(pixel);
             }
         }
@@ -123,24 +493,63 @@ impl Image {
 
         Layer::from_slice(&pixel_vec, self.width, self.height)
     }
+
+    fn stats(&self, palette: &Palette) -> Vec<LayerStats> {
+        let mut stats: Vec<LayerStats> = self
+            .layers
+            .iter()
+            .map(|layer| LayerStats {
+                histogram: layer.histogram(),
+                opaque: layer
+                    .data
+                    .iter()
+                    .filter(|&&d| palette.is_transparent(d) == false)
+                    .count(),
+                visible: 0,
+            })
+            .collect();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let front = self
+                    .layers
+                    .iter()
+                    .position(|layer| palette.is_transparent(layer.pixel(row, col)) == false);
+                if let Some(idx) = front {
+                    stats[idx].visible += 1;
+                }
+            }
+        }
+        stats
+    }
 }
 
 #[aoc(day8, part2)]
-pub fn solve(input: &str) -> Layer {
-    let input_vec: Vec<u32> = input
-        .trim()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap())
-        .collect();
-    let image = Image::from_slice(&input_vec, 25, 6);
-    // image.display_all();
+pub fn solve(input: &str) -> String {
+    let image = match Image::parse(input, 25, 6) {
+        Ok(image) => image,
+        Err(e) => panic!("{}", e),
+    };
 
-    image.stack_layers()
+    let layer = image.stack_layers();
+    match layer.text() {
+        Ok(code) => code,
+        Err(unknown) => {
+            unknown.iter().for_each(|glyph| println!("{}", glyph));
+            panic!("Could not read the image:{}", layer);
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::fs;
+
+    fn pixels_from_string(s: &str) -> Vec<Vec<bool>> {
+        s.lines()
+            .map(|line| line.chars().map(|c| c == '#').collect())
+            .collect()
+    }
 
     #[test]
     fn test_image() {
@@ -157,4 +566,201 @@ mod test {
         let stacked_image: Layer = image.stack_layers();
         assert_eq!(stacked_image.data, vec![0, 1, 1, 0]);
     }
+
+    #[test]
+    fn test_flatten() {
+        // Only 2 is transparent in the puzzle's palette, and a pixel that is transparent all the way down stays 2
+        let image = Image::parse("32221129", 2, 2).unwrap();
+        assert_eq!(image.stack_layers().data, vec![3, 1, 2, 9]);
+
+        let mut palette = Palette::new((0, 0, 0));
+        palette.set(0, Some((0, 0, 0)));
+        palette.set(1, Some((255, 255, 255)));
+        let image = Image::parse("5271", 2, 1).unwrap();
+        assert_eq!(image.flatten(&palette).data, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let image = Image::parse("0222112222120000\n", 2, 2).unwrap();
+        assert_eq!(image.layers.len(), 4);
+        assert_eq!(image.encode(), "0222112222120000");
+        assert_eq!(image.stack_layers().data, vec![0, 1, 1, 0]);
+
+        assert_eq!(
+            Image::parse("02221x", 2, 2).err(),
+            Some(SifError::BadDigit(5, 'x'))
+        );
+        assert_eq!(
+            Image::parse("022211", 2, 2).err(),
+            Some(SifError::BadLength(6, 4))
+        );
+        assert_eq!(
+            Image::parse("", 2, 2).err(),
+            Some(SifError::BadLength(0, 4))
+        );
+        assert_eq!(Image::parse("0222", 0, 2).err(), Some(SifError::EmptySize));
+    }
+
+    #[test]
+    fn test_from_layers() {
+        let layers = vec![
+            Layer::from_slice(&[0, 2, 2, 2], 2, 2),
+            Layer::from_slice(&[1, 1, 2, 2], 2, 2),
+        ];
+        let image = Image::from_layers(layers).unwrap();
+        assert_eq!(image.encode(), "02221122");
+
+        let layers = vec![
+            Layer::from_slice(&[0, 2, 2, 2], 2, 2),
+            Layer::from_slice(&[1, 1, 2, 2], 4, 1),
+        ];
+        assert_eq!(
+            Image::from_layers(layers).err(),
+            Some(SifError::SizeMismatch(1))
+        );
+        assert_eq!(Image::from_layers(vec![]).err(), Some(SifError::EmptySize));
+    }
+
+    #[test]
+    fn test_pnm() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        let layer = image.stack_layers();
+        let palette = Palette::space();
+        assert_eq!(layer.to_pgm(&palette), "P2\n2 2\n255\n0 255\n255 0\n");
+        assert_eq!(
+            layer.to_ppm(&palette),
+            "P3\n2 2\n255\n0 0 0  255 255 255\n255 255 255  0 0 0\n"
+        );
+        let round_trip = Layer::from_pnm(&layer.to_pgm(&palette), &palette).unwrap();
+        assert_eq!(round_trip.data, layer.data);
+
+        // Transparent pixels show the background and read back as the first transparent digit
+        let mut palette = Palette::new((0, 0, 64));
+        palette.set(3, Some((255, 0, 0)));
+        palette.set(4, Some((0, 255, 0)));
+        let layer = Layer::from_slice(&[3, 4, 9, 4, 3, 3], 3, 2);
+        let ppm = layer.to_ppm(&palette);
+        assert_eq!(
+            ppm,
+            "P3\n3 2\n255\n255 0 0  0 255 0  0 0 64\n0 255 0  255 0 0  255 0 0\n"
+        );
+        let round_trip = Layer::from_pnm(&ppm, &palette).unwrap();
+        assert_eq!(round_trip.data, vec![3, 4, 0, 4, 3, 3]);
+
+        let with_comment = "P3\n# made by hand\n1 1\n255\n0 255 0\n";
+        assert_eq!(
+            Layer::from_pnm(with_comment, &palette).unwrap().data,
+            vec![4]
+        );
+
+        assert_eq!(
+            Layer::from_pnm("P6\n1 1\n255\n", &palette).err(),
+            Some(SifError::BadHeader("P6".to_string()))
+        );
+        assert_eq!(
+            Layer::from_pnm("P3\n1 1\n255\n1 2 3\n", &palette).err(),
+            Some(SifError::UnknownColor(0))
+        );
+        assert_eq!(
+            Layer::from_pnm("P2\n2 1\n255\n0 256\n", &Palette::space()).err(),
+            Some(SifError::BadValue(1, "256".to_string()))
+        );
+        assert_eq!(
+            Layer::from_pnm("P2\n2 1\n255\n0\n", &Palette::space()).err(),
+            Some(SifError::BadLength(1, 2))
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let image = Image::parse("0222112222120000", 2, 2).unwrap();
+        let stats = image.stats(&Palette::space());
+        assert_eq!(
+            stats[0],
+            LayerStats {
+                histogram: [1, 0, 3, 0, 0, 0, 0, 0, 0, 0],
+                opaque: 1,
+                visible: 1,
+            }
+        );
+        assert_eq!(
+            stats.iter().map(|s| s.visible).collect::<Vec<_>>(),
+            vec![1, 1, 1, 1]
+        );
+        assert_eq!(stats[0].most_common(), 2);
+        assert_eq!(stats[3].coverage(), 1.0);
+        assert_eq!(stats[1].coverage(), 0.5);
+    }
+
+    #[test]
+    fn test_read_glyphs() {
+        let pixels = pixels_from_string(
+            "\
+#..#.####.#...#
+#..#.#....#...#
+####.###...#.#.
+#..#.#......#..
+#..#.#......#..
+#..#.####...#..",
+        );
+        assert_eq!(read_glyphs(&pixels), Ok("HEY".to_string()));
+
+        let pixels = pixels_from_string(
+            "\
+..##..##..#...#####
+...#.#..#.#...##...
+...#.#.....#.#.###.
+...#.#.##...#..#...
+#..#.#..#...#..#...
+.##...###...#..####",
+        );
+        assert_eq!(read_glyphs(&pixels), Ok("JGYE".to_string()));
+
+        let pixels = pixels_from_string(
+            "\
+.#....##.
+##...#..#
+.#...#..#
+.#...####
+.#...#..#
+###..#..#",
+        );
+        assert_eq!(
+            read_glyphs(&pixels),
+            Err(vec![UnknownGlyph {
+                index: 0,
+                column: 0,
+                rows: [".#.", "##.", ".#.", ".#.", ".#.", "###"]
+                    .iter()
+                    .map(|row| row.to_string())
+                    .collect(),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_read_font() {
+        // Every letter drawn at the start of its own cell, as the puzzles do
+        let pixels: Vec<Vec<bool>> = (0..GLYPH_HEIGHT)
+            .map(|row| {
+                FONT.iter()
+                    .flat_map(|(_, rows)| {
+                        let mut cell: Vec<bool> = rows[row].chars().map(|c| c == '#').collect();
+                        cell.resize(GLYPH_WIDTH, false);
+                        cell
+                    })
+                    .collect()
+            })
+            .collect();
+        let letters: String = FONT.iter().map(|&(letter, _)| letter).collect();
+        assert_eq!(read_glyphs(&pixels), Ok(letters));
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = fs::read_to_string("input/2019/day8.txt")
+            .expect("Something went wrong reading the file");
+        assert_eq!(solve(&input), "YEHEF");
+    }
 }
@@ -98,7 +98,53 @@
 */
 
 use num::integer::gcd;
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+// The step from one asteroid towards another, reduced so that everything along the same line of
+// sight shares it. Directions order clockwise from straight up, with y growing downwards.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Direction {
+    dx: i32,
+    dy: i32,
+}
+
+impl Direction {
+    fn between(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+        if from == to {
+            return None;
+        }
+        let diff = (to.0 - from.0, to.1 - from.1);
+        let step_gcd = gcd(diff.0, diff.1);
+        Some(Direction {
+            dx: diff.0 / step_gcd,
+            dy: diff.1 / step_gcd,
+        })
+    }
+
+    // 0 from straight up round to just before straight down, 1 for the rest of the turn
+    fn half(&self) -> u8 {
+        if self.dx > 0 || (self.dx == 0 && self.dy < 0) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Ord for Direction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Within a half turn, `other` is further clockwise exactly when the cross product is positive
+        let cross = self.dx as i64 * other.dy as i64 - self.dy as i64 * other.dx as i64;
+        self.half().cmp(&other.half()).then_with(|| 0.cmp(&cross))
+    }
+}
+
+impl PartialOrd for Direction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 struct AsteroidMap {
     data: HashSet<(i32, i32)>,
@@ -124,49 +170,57 @@ impl AsteroidMap {
         AsteroidMap { data }
     }
 
+    // Visible when it's the nearest asteroid along its line of sight
     fn is_visible(&self, point_a: (i32, i32), point_b: (i32, i32)) -> bool {
-        if point_a == point_b {
-            // Safe short circuit -- self is never visible
-            return false;
-        }
-
-        let diff = (point_b.0 - point_a.0, point_b.1 - point_a.1);
-        let step_gcd = gcd(diff.0, diff.1);
-        let step = (diff.0 / step_gcd, diff.1 / step_gcd);
-
-        let mut curr = point_a; // Start at the source; skip it.
-        loop {
-            curr.0 += step.0;
-            curr.1 += step.1;
-
-            if curr == point_b {
-                // Found destination; skip it.
-                return true;
+        match Direction::between(point_a, point_b) {
+            Some(direction) => {
+                let nearest = self
+                    .lines_of_sight(point_a)
+                    .get(&direction)
+                    .map(|line| line[0]);
+                nearest == Some(point_b)
             }
+            None => false, // Self is never visible
+        }
+    }
 
-            if self.data.contains(&curr) {
-                return false;
+    // Every other asteroid grouped by direction from the given location, nearest first
+    fn lines_of_sight(&self, from_location: (i32, i32)) -> BTreeMap<Direction, Vec<(i32, i32)>> {
+        let mut lines: BTreeMap<Direction, Vec<(i32, i32)>> = BTreeMap::new();
+        for &asteroid in self.data.iter() {
+            if let Some(direction) = Direction::between(from_location, asteroid) {
+                lines.entry(direction).or_default().push(asteroid);
             }
         }
+        for line in lines.values_mut() {
+            line.sort_by_key(|&(x, y)| (x - from_location.0).abs() + (y - from_location.1).abs());
+        }
+        lines
     }
 
     fn get_visible(&self, from_location: (i32, i32)) -> Vec<(i32, i32)> {
-        self.data
-            .iter()
-            .filter(|&&asteroid| self.is_visible(asteroid, from_location))
-            .copied()
+        self.lines_of_sight(from_location)
+            .values()
+            .map(|line| line[0])
             .collect()
     }
 
+    // One asteroid is visible along each distinct direction
     fn count_visible(&self, from_location: (i32, i32)) -> usize {
-        self.get_visible(from_location).len()
+        let directions: HashSet<Direction> = self
+            .data
+            .iter()
+            .filter_map(|&asteroid| Direction::between(from_location, asteroid))
+            .collect();
+        directions.len()
     }
 
+    // Ties go to the asteroid nearest the top, then the left
     fn best_station(&self) -> (i32, i32) {
         let best_asteroid = *self
             .data
             .iter()
-            .max_by_key(|&&asteroid| self.count_visible(asteroid))
+            .max_by_key(|&&asteroid| (self.count_visible(asteroid), -asteroid.1, -asteroid.0))
             .unwrap();
         best_asteroid
     }
@@ -301,6 +355,12 @@ mod test {
         for item in &test_list {
             assert!(visible_list.contains(&item));
         }
+
+        // Clockwise from straight up
+        assert_eq!(
+            visible_list,
+            [(3, 1), (3, 2), (4, 3), (3, 3), (2, 3), (2, 4), (1, 3)]
+        );
     }
 
     #[test]
@@ -60,8 +60,53 @@
 */
 
 use num::integer::gcd;
-use std::collections::HashSet;
-use std::f32;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+// The step from one asteroid towards another, reduced so that everything along the same line of
+// sight shares it. Directions order clockwise from straight up, with y growing downwards.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Direction {
+    dx: i32,
+    dy: i32,
+}
+
+impl Direction {
+    fn between(from: (i32, i32), to: (i32, i32)) -> Option<Direction> {
+        if from == to {
+            return None;
+        }
+        let diff = (to.0 - from.0, to.1 - from.1);
+        let step_gcd = gcd(diff.0, diff.1);
+        Some(Direction {
+            dx: diff.0 / step_gcd,
+            dy: diff.1 / step_gcd,
+        })
+    }
+
+    // 0 from straight up round to just before straight down, 1 for the rest of the turn
+    fn half(&self) -> u8 {
+        if self.dx > 0 || (self.dx == 0 && self.dy < 0) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Ord for Direction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Within a half turn, `other` is further clockwise exactly when the cross product is positive
+        let cross = self.dx as i64 * other.dy as i64 - self.dy as i64 * other.dx as i64;
+        self.half().cmp(&other.half()).then_with(|| 0.cmp(&cross))
+    }
+}
+
+impl PartialOrd for Direction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 struct AsteroidMap {
     data: HashSet<(i32, i32)>,
@@ -87,97 +132,91 @@ impl AsteroidMap {
         AsteroidMap { data }
     }
 
+    // Visible when it's the nearest asteroid along its line of sight
     fn is_visible(&self, point_a: (i32, i32), point_b: (i32, i32)) -> bool {
-        if point_a == point_b {
-            // Safe short circuit -- self is never visible
-            return false;
-        }
-
-        let diff = (point_b.0 - point_a.0, point_b.1 - point_a.1);
-        let step_gcd = gcd(diff.0, diff.1);
-        let step = (diff.0 / step_gcd, diff.1 / step_gcd);
-
-        let mut curr = point_a; // Start at the source; skip it.
-        loop {
-            curr.0 += step.0;
-            curr.1 += step.1;
-
-            if curr == point_b {
-                // Found destination; skip it.
-                return true;
+        match Direction::between(point_a, point_b) {
+            Some(direction) => {
+                let nearest = self
+                    .lines_of_sight(point_a)
+                    .get(&direction)
+                    .map(|line| line[0]);
+                nearest == Some(point_b)
             }
+            None => false, // Self is never visible
+        }
+    }
 
-            if self.data.contains(&curr) {
-                return false;
+    // Every other asteroid grouped by direction from the given location, nearest first
+    fn lines_of_sight(&self, from_location: (i32, i32)) -> BTreeMap<Direction, Vec<(i32, i32)>> {
+        let mut lines: BTreeMap<Direction, Vec<(i32, i32)>> = BTreeMap::new();
+        for &asteroid in self.data.iter() {
+            if let Some(direction) = Direction::between(from_location, asteroid) {
+                lines.entry(direction).or_default().push(asteroid);
             }
         }
+        for line in lines.values_mut() {
+            line.sort_by_key(|&(x, y)| (x - from_location.0).abs() + (y - from_location.1).abs());
+        }
+        lines
     }
 
     fn get_visible(&self, from_location: (i32, i32)) -> Vec<(i32, i32)> {
-        self.data
-            .iter()
-            .filter(|&&asteroid| self.is_visible(asteroid, from_location))
-            .copied()
+        self.lines_of_sight(from_location)
+            .values()
+            .map(|line| line[0])
             .collect()
     }
 
+    // One asteroid is visible along each distinct direction
     fn count_visible(&self, from_location: (i32, i32)) -> usize {
-        self.get_visible(from_location).len()
+        let directions: HashSet<Direction> = self
+            .data
+            .iter()
+            .filter_map(|&asteroid| Direction::between(from_location, asteroid))
+            .collect();
+        directions.len()
     }
 
+    // Ties go to the asteroid nearest the top, then the left
     fn best_station(&self) -> (i32, i32) {
         let best_asteroid = *self
             .data
             .iter()
-            .max_by_key(|&&asteroid| self.count_visible(asteroid))
+            .max_by_key(|&&asteroid| (self.count_visible(asteroid), -asteroid.1, -asteroid.0))
             .unwrap();
         best_asteroid
     }
 
-    fn vaporize(&mut self, from_location: (i32, i32), count: usize) -> (i32, i32) {
-        let mut num_vaporized = 0;
-
-        loop {
-            let mut visible = self.get_visible(from_location);
-            if visible.is_empty() == true {
-                return from_location;
-            }
-
-            // Take all visible asteroids and sort them in angular order
-            visible.sort_by(|&a, &b| {
-                angle_between_points(from_location, a)
-                    .partial_cmp(&angle_between_points(from_location, b))
-                    .unwrap()
-            });
-            // visible.iter().for_each(|&p| println!("{:?}: {}", p, angle_between_points(from_location, p)));
-
-            // Remove asteroids one by one
-            for &v in &visible {
-                self.data.remove(&v);
-
-                if num_vaporized == count {
-                    return v;
-                } else {
-                    num_vaporized += 1;
-                }
+    // The order a laser at the given location destroys every other asteroid. Each rotation takes
+    // the nearest remaining asteroid in each direction.
+    fn vaporization_order(&self, from_location: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut order: Vec<(usize, Direction, (i32, i32))> = Vec::new();
+        for (direction, line) in self.lines_of_sight(from_location) {
+            for (rotation, &asteroid) in line.iter().enumerate() {
+                order.push((rotation, direction, asteroid));
             }
         }
+        order.sort_by_key(|&(rotation, direction, _)| (rotation, direction));
+        order.into_iter().map(|(_, _, asteroid)| asteroid).collect()
     }
-}
 
-fn angle_between_points(point_a: (i32, i32), point_b: (i32, i32)) -> f32 {
-    let diff = (point_b.0 - point_a.0, point_b.1 - point_a.1);
-    let angle = (diff.1 as f32).atan2(diff.0 as f32).to_degrees();
-    let mut adjusted_angle = angle + 90.0;
-    if adjusted_angle < 0.0 {
-        adjusted_angle += 360.0;
+    // Vaporizes up to and including the asteroid at `count` in the order, and returns it.
+    // If there are not that many asteroids, returns the station.
+    fn vaporize(&mut self, from_location: (i32, i32), count: usize) -> (i32, i32) {
+        let order = self.vaporization_order(from_location);
+        for v in order.iter().take(count + 1) {
+            self.data.remove(v);
+        }
+        match order.get(count) {
+            Some(&v) => v,
+            None => from_location,
+        }
     }
-    adjusted_angle
 }
 
 #[aoc(day10, part2)]
 pub fn solve(input: &str) -> i32 {
-    let mut asteroids = AsteroidMap::from_string(&input);
+    let mut asteroids = AsteroidMap::from_string(input);
     // println!("{:?}", asteroids.data);
 
     let best_station = asteroids.best_station();
@@ -420,38 +459,61 @@ mod test {
     }
 
     #[test]
-    fn test_angle_between_points() {
-        let angle = angle_between_points((0, 0), (0, -1));
-        let abs_difference = (angle - 0.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (1, -1));
-        let abs_difference = (angle - 45.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (1, 0));
-        let abs_difference = (angle - 90.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (1, 1));
-        let abs_difference = (angle - 135.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (0, 1));
-        let abs_difference = (angle - 180.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (-1, 1));
-        let abs_difference = (angle - 225.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (-1, 0));
-        let abs_difference = (angle - 270.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
-
-        let angle = angle_between_points((0, 0), (-1, -1));
-        let abs_difference = (angle - 315.0).abs();
-        assert!(abs_difference <= f32::EPSILON);
+    fn test_direction_order() {
+        let directions: Vec<Direction> = [
+            (0, -1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+        ]
+        .iter()
+        .map(|&p| Direction::between((0, 0), p).unwrap())
+        .collect();
+        let mut sorted = directions.clone();
+        sorted.reverse();
+        sorted.sort();
+        assert_eq!(sorted, directions);
+
+        // Directions that would round to the same f32 angle still compare exactly
+        let a = Direction::between((0, 0), (100_000, -99_999)).unwrap();
+        let b = Direction::between((0, 0), (99_999, -99_998)).unwrap();
+        assert_eq!(a.cmp(&b), Ordering::Less);
+        assert_eq!(
+            Direction::between((3, 3), (9, 12)),
+            Direction::between((0, 0), (2, 3))
+        );
+    }
+
+    #[test]
+    fn test_vaporization_order() {
+        let input_str = "
+.#....#####...#..
+##...##.#####..##
+##...#...#.#####.
+..#.....#...###..
+..#.#.....#....##
+";
+        let asteroids = AsteroidMap::from_string(&input_str);
+        let order = asteroids.vaporization_order((8, 3));
+        assert_eq!(order.len(), asteroids.data.len() - 1);
+        assert_eq!(&order[..4], &[(8, 1), (9, 0), (9, 1), (10, 0)]);
+        assert_eq!(&order[35..], &[(14, 3)]);
+
+        // Asteroids on the same line of sight are taken one per rotation, nearest first
+        let asteroids = AsteroidMap::from_string("##.#.##");
+        assert_eq!(
+            asteroids.vaporization_order((3, 0)),
+            vec![(5, 0), (1, 0), (6, 0), (0, 0)]
+        );
+        let asteroids = AsteroidMap::from_string("#\n#\n#\n#");
+        assert_eq!(
+            asteroids.vaporization_order((0, 1)),
+            vec![(0, 0), (0, 2), (0, 3)]
+        );
     }
 
     #[test]
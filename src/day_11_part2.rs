@@ -8,6 +8,7 @@
 */
 
 use std::collections::HashMap;
+use std::fmt;
 
 struct Program {
     code: Vec<i64>,
@@ -285,6 +286,7 @@ impl Program {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Color {
     Black,
     White,
@@ -314,6 +316,7 @@ impl Color {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum AbsDirection {
     North,
     South,
@@ -442,47 +445,257 @@ impl Robot {
     }
 }
 
-fn run_program_with_robot(program: &mut Program, robot: &mut Robot) {
-    while program.halted == false {
-        program.run_with_pause();
-
-        if program.input_needed == true {
-            let current_color = robot.get_color_curr();
-            program.input.push(current_color.value() as i64);
-        } else if program.output.len() >= 2 {
-            let color_value = program.output.remove(0);
-            let color = Color::from_value(color_value);
-            robot.set_color(color);
-
-            let rel_dir_value = program.output.remove(0);
-            let rel_dir = RelDirection::from_value(rel_dir_value);
-            robot.turn(rel_dir);
-            robot.move_forward(1);
+// The capital-letter font the puzzles draw with. Each glyph sits in a cell 6 pixels tall and 5 wide,
+// which leaves a blank column after every letter except the wide Y. The narrow I is only 3 wide.
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_WIDTH: usize = 5;
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq)]
+struct UnknownGlyph {
+    index: usize,
+    column: usize,
+    rows: Vec<String>,
+}
+
+impl fmt::Display for UnknownGlyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Unknown glyph {} at column {}:",
+            self.index + 1,
+            self.column
+        )?;
+        for row in self.rows.iter() {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+// Cuts the lit pixels into glyphs at blank columns, at most a cell wide, and looks each one up in the font
+fn read_glyphs(pixels: &[Vec<bool>]) -> Result<String, Vec<UnknownGlyph>> {
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |row: usize, col: usize| pixels[row].get(col) == Some(&true);
+    let blank = |col: usize| (0..pixels.len()).all(|row| lit(row, col) == false);
+
+    let mut text = String::new();
+    let mut unknown = Vec::new();
+    let mut col = 0;
+    while let Some(start) = (col..width).find(|&c| blank(c) == false) {
+        let limit = (start + GLYPH_WIDTH).min(width);
+        let end = (start..limit).find(|&c| blank(c) == true).unwrap_or(limit);
+        let rows: Vec<String> = (0..pixels.len())
+            .map(|row| {
+                (start..end)
+                    .map(|c| if lit(row, c) == true { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        let glyph = FONT
+            .iter()
+            .find(|(_, font_rows)| font_rows.iter().eq(rows.iter()))
+            .map(|&(letter, _)| letter);
+        match glyph {
+            Some(letter) => text.push(letter),
+            None => {
+                unknown.push(UnknownGlyph {
+                    index: text.len(),
+                    column: start,
+                    rows,
+                });
+                text.push('?');
+            }
         }
+        col = end;
+    }
+
+    if unknown.is_empty() == true {
+        Ok(text)
+    } else {
+        Err(unknown)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stroke {
+    panel: (i64, i64),
+    color: Color,
+    step: usize,
+}
+
+struct HullPainter {
+    program: Program,
+    robot: Robot,
+    start_color: Color,
+    history: Vec<Stroke>,
+}
+
+impl HullPainter {
+    fn new(code: &[i64], start_color: Color, start_direction: AbsDirection) -> Self {
+        let mut robot = Robot::new();
+        robot.abs_direction = start_direction;
+        robot.set_color(start_color);
+
+        Self {
+            program: Program::new(code, &[]),
+            robot,
+            start_color,
+            history: Vec::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        while self.program.halted == false {
+            self.program.run_with_pause();
+
+            if self.program.input_needed == true {
+                let current_color = self.robot.get_color_curr();
+                self.program.input.push(current_color.value());
+            } else if self.program.output.len() >= 2 {
+                let color = Color::from_value(self.program.output.remove(0));
+                self.history.push(Stroke {
+                    panel: self.robot.location,
+                    color,
+                    step: self.history.len(),
+                });
+                self.robot.set_color(color);
+
+                let rel_dir = RelDirection::from_value(self.program.output.remove(0));
+                self.robot.turn(rel_dir);
+                self.robot.move_forward(1);
+            }
+        }
+    }
+
+    // The hull as it was before the given step was painted
+    fn hull_at(&self, step: usize) -> HashMap<(i64, i64), Color> {
+        let mut hull: HashMap<(i64, i64), Color> = HashMap::new();
+        hull.insert((0, 0), self.start_color);
+        for stroke in self.history.iter().take_while(|s| s.step < step) {
+            hull.insert(stroke.panel, stroke.color);
+        }
+        hull
+    }
+
+    // Smallest (min, max) corners covering every panel the robot ever touched
+    fn bounds(&self) -> ((i64, i64), (i64, i64)) {
+        self.history
+            .iter()
+            .map(|s| s.panel)
+            .fold(((0, 0), (0, 0)), |(min, max), (x, y)| {
+                ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+            })
+    }
+
+    // Reads the white panels of the finished hull as text
+    fn text(&self) -> Result<String, Vec<UnknownGlyph>> {
+        let hull = self.hull_at(self.history.len());
+        let (min, max) = self.bounds();
+        let pixels: Vec<Vec<bool>> = (min.1..=max.1)
+            .map(|y| {
+                (min.0..=max.0)
+                    .map(|x| hull.get(&(x, y)) == Some(&Color::White))
+                    .collect()
+            })
+            .collect();
+        read_glyphs(&pixels)
+    }
+
+    // Plain PBM, where 1 is black
+    fn to_pbm(&self) -> String {
+        let hull = self.hull_at(self.history.len());
+        let (min, max) = self.bounds();
+        let mut image = format!("P1\n{} {}\n", max.0 - min.0 + 1, max.1 - min.1 + 1);
+        for y in min.1..=max.1 {
+            let row: Vec<&str> = (min.0..=max.0)
+                .map(|x| match hull.get(&(x, y)) {
+                    Some(Color::White) => "0",
+                    _ => "1",
+                })
+                .collect();
+            image.push_str(&row.join(" "));
+            image.push('\n');
+        }
+        image
+    }
+
+    // Plain PGM of the hull before the given step. The robot is shown in grey.
+    fn to_pgm(&self, step: usize, show_robot: bool) -> String {
+        let hull = self.hull_at(step);
+        let robot = match self.history.get(step) {
+            Some(stroke) => stroke.panel,
+            None => self.robot.location,
+        };
+        let (min, max) = self.bounds();
+        let mut image = format!("P2\n{} {}\n255\n", max.0 - min.0 + 1, max.1 - min.1 + 1);
+        for y in min.1..=max.1 {
+            let row: Vec<&str> = (min.0..=max.0)
+                .map(|x| match hull.get(&(x, y)) {
+                    _ if show_robot == true && robot == (x, y) => "128",
+                    Some(Color::White) => "255",
+                    _ => "0",
+                })
+                .collect();
+            image.push_str(&row.join(" "));
+            image.push('\n');
+        }
+        image
+    }
+
+    // One frame every `steps_per_frame` strokes, plus the finished hull. All frames are the same size.
+    fn animation(&self, steps_per_frame: usize) -> Vec<String> {
+        let mut frames: Vec<String> = (0..self.history.len())
+            .step_by(steps_per_frame.max(1))
+            .map(|step| self.to_pgm(step, true))
+            .collect();
+        frames.push(self.to_pgm(self.history.len(), false));
+        frames
     }
 }
 
 #[aoc(day11, part2)]
-pub fn solve(input: &str) -> usize {
+pub fn solve(input: &str) -> String {
     let code: Vec<i64> = input
         .trim()
         .split(',')
         .map(|s| s.parse::<i64>().unwrap())
         .collect();
-    let mut program = Program::new(&code, &[]);
-
-    let mut robot = Robot::new();
-    robot.set_color(Color::White);
-
-    run_program_with_robot(&mut program, &mut robot);
-    robot.display();
-    println!("Painted panels: {}", robot.panels.len());
-    robot.panels.len()
+    let mut painter = HullPainter::new(&code, Color::White, AbsDirection::North);
+    painter.run();
+    match painter.text() {
+        Ok(code) => code,
+        Err(unknown) => {
+            unknown.iter().for_each(|glyph| println!("{}", glyph));
+            painter.robot.display();
+            panic!("Could not read the hull");
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_program() {
@@ -503,4 +716,79 @@ mod test {
         program.run();
         assert_eq!(program.output, [1125899906842624]);
     }
+
+    fn example_painter() -> HullPainter {
+        // Outputs the example's paint and turn instructions without looking at the camera
+        let outputs = [1, 0, 0, 0, 1, 0, 1, 0, 0, 1, 1, 0, 1, 0];
+        let mut code: Vec<i64> = outputs.iter().flat_map(|&o| vec![104, o]).collect();
+        code.push(99);
+
+        let mut painter = HullPainter::new(&code, Color::Black, AbsDirection::North);
+        painter.run();
+        painter
+    }
+
+    #[test]
+    fn test_hull_painter() {
+        let painter = example_painter();
+        assert_eq!(painter.history.len(), 7);
+        assert_eq!(
+            painter.history[4],
+            Stroke {
+                panel: (0, 0),
+                color: Color::Black,
+                step: 4
+            }
+        );
+        assert_eq!(painter.robot.panels.len(), 6);
+        assert_eq!(painter.robot.location, (0, -1));
+        assert_eq!(painter.bounds(), ((-1, -1), (1, 1)));
+
+        assert_eq!(painter.hull_at(4).get(&(0, 0)), Some(&Color::White));
+        assert_eq!(painter.hull_at(5).get(&(0, 0)), Some(&Color::Black));
+    }
+
+    #[test]
+    fn test_hull_painter_images() {
+        let painter = example_painter();
+        assert_eq!(painter.to_pbm(), "P1\n3 3\n1 1 0\n1 1 0\n0 0 1\n");
+        assert_eq!(
+            painter.to_pgm(0, true),
+            "P2\n3 3\n255\n0 0 0\n0 128 0\n0 0 0\n"
+        );
+
+        let frames = painter.animation(3);
+        assert_eq!(frames.len(), 4); // Steps 0, 3 and 6, then the finished hull
+        assert_eq!(frames[1], "P2\n3 3\n255\n0 0 0\n0 255 0\n255 128 0\n");
+        assert_eq!(frames[3], "P2\n3 3\n255\n0 0 255\n0 0 255\n255 255 0\n");
+
+        let painter = HullPainter::new(&[99], Color::White, AbsDirection::East);
+        assert_eq!(painter.robot.abs_direction, AbsDirection::East);
+        assert_eq!(painter.to_pbm(), "P1\n1 1\n0\n");
+    }
+
+    #[test]
+    fn test_read_font() {
+        // Every letter drawn at the start of its own cell, as the puzzles do
+        let pixels: Vec<Vec<bool>> = (0..GLYPH_HEIGHT)
+            .map(|row| {
+                FONT.iter()
+                    .flat_map(|(_, rows)| {
+                        let mut cell: Vec<bool> = rows[row].chars().map(|c| c == '#').collect();
+                        cell.resize(GLYPH_WIDTH, false);
+                        cell
+                    })
+                    .collect()
+            })
+            .collect();
+        let letters: String = FONT.iter().map(|&(letter, _)| letter).collect();
+        assert_eq!(read_glyphs(&pixels), Ok(letters));
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = fs::read_to_string("input/2019/day11.txt")
+            .expect("Something went wrong reading the file");
+        assert_eq!(solve(&input), "JHARBGCU");
+    }
 }
@@ -40,18 +40,49 @@
     How many steps does it take to reach the first state that exactly matches a previous state?
 */
 
-use num::integer::lcm;
+use num::integer::gcd;
 use regex::Regex;
 use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+enum SimError {
+    NoBodies,
+    DimensionMismatch(usize),
+    Overflow { body: usize, axis: usize },
+    EnergyOverflow { body: usize },
+    // Combining the periods up to this axis doesn't fit in a u64
+    PeriodOverflow { axis: usize },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimError::NoBodies => write!(f, "No bodies to simulate"),
+            SimError::DimensionMismatch(body) => {
+                write!(f, "Body {} has a different number of dimensions", body)
+            }
+            SimError::Overflow { body, axis } => {
+                write!(f, "Body {} overflowed along axis {}", body, axis)
+            }
+            SimError::EnergyOverflow { body } => {
+                write!(f, "Energy overflowed at body {}", body)
+            }
+            SimError::PeriodOverflow { axis } => {
+                write!(f, "Repeat period overflowed at axis {}", axis)
+            }
+        }
+    }
+}
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct Component {
-    position: i32,
-    velocity: i32,
+    position: i64,
+    velocity: i64,
 }
 
 impl Component {
-    fn from_position(position: i32) -> Component {
+    fn from_position(position: i64) -> Component {
         Component {
             position,
             velocity: 0,
@@ -59,37 +90,47 @@ impl Component {
     }
 }
 
-struct SpaceObjects {
-    x: Vec<Component>,
-    y: Vec<Component>,
-    z: Vec<Component>,
-}
-
+// Any number of bodies in any number of dimensions. Gravity along one axis only depends on
+// positions along that axis, so each axis is stored and stepped on its own.
 struct Sim {
-    objects: SpaceObjects,
+    axes: Vec<Vec<Component>>,
 }
 
 impl Sim {
-    fn from_positions(positions: Vec<(i32, i32, i32)>) -> Sim {
-        Sim {
-            objects: SpaceObjects {
-                x: positions
-                    .iter()
-                    .map(|&p| Component::from_position(p.0))
-                    .collect(),
-                y: positions
-                    .iter()
-                    .map(|&p| Component::from_position(p.1))
-                    .collect(),
-                z: positions
-                    .iter()
-                    .map(|&p| Component::from_position(p.2))
-                    .collect(),
-            },
+    fn from_positions(positions: Vec<Vec<i64>>) -> Result<Sim, SimError> {
+        let dimensions = match positions.first() {
+            Some(p) => p.len(),
+            None => return Err(SimError::NoBodies),
+        };
+        if let Some(body) = positions.iter().position(|p| p.len() != dimensions) {
+            return Err(SimError::DimensionMismatch(body));
         }
+
+        Ok(Sim {
+            axes: (0..dimensions)
+                .map(|axis| {
+                    positions
+                        .iter()
+                        .map(|p| Component::from_position(p[axis]))
+                        .collect()
+                })
+                .collect(),
+        })
     }
 
-    fn get_gravity(a: i32, b: i32) -> i32 {
+    fn body_count(&self) -> usize {
+        self.axes.first().map(|axis| axis.len()).unwrap_or(0)
+    }
+
+    fn position(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis[body].position).collect()
+    }
+
+    fn velocity(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis[body].velocity).collect()
+    }
+
+    fn get_gravity(a: i64, b: i64) -> i64 {
         match a.cmp(&b) {
             Ordering::Less => 1,
             Ordering::Greater => -1,
@@ -97,87 +138,191 @@ impl Sim {
         }
     }
 
-    fn single_step_component(objects_n: &mut Vec<Component>) {
+    // Returns the body that overflowed, if any
+    fn single_step_component(objects_n: &mut [Component]) -> Result<(), usize> {
         for i in 0..objects_n.len() {
-            for j in 0..objects_n.len() {
-                let gravity = Sim::get_gravity(objects_n[i].position, objects_n[j].position);
-                objects_n[i].velocity += gravity;
-            }
+            let pull: i64 = objects_n
+                .iter()
+                .map(|other| Sim::get_gravity(objects_n[i].position, other.position))
+                .sum();
+            objects_n[i].velocity = objects_n[i].velocity.checked_add(pull).ok_or(i)?;
         }
 
-        objects_n
-            .iter_mut()
-            .for_each(|obj| obj.position += obj.velocity);
+        for (i, obj) in objects_n.iter_mut().enumerate() {
+            obj.position = obj.position.checked_add(obj.velocity).ok_or(i)?;
+        }
+        Ok(())
     }
 
-    fn single_step(&mut self) {
-        Sim::single_step_component(&mut self.objects.x);
-        Sim::single_step_component(&mut self.objects.y);
-        Sim::single_step_component(&mut self.objects.z);
+    fn single_step(&mut self) -> Result<(), SimError> {
+        for (axis, objects_n) in self.axes.iter_mut().enumerate() {
+            Sim::single_step_component(objects_n)
+                .map_err(|body| SimError::Overflow { body, axis })?;
+        }
+        Ok(())
     }
 
-    fn step(&mut self, num_steps: u32) {
+    fn step(&mut self, num_steps: u32) -> Result<(), SimError> {
         for _ in 0..num_steps {
-            self.single_step();
+            self.single_step()?;
         }
+        Ok(())
     }
 
-    fn find_repeat_component(objects_n: &mut Vec<Component>) -> u64 {
+    fn abs_sum(values: &[i64], body: usize) -> Result<i64, SimError> {
+        values.iter().enumerate().try_fold(0i64, |sum, (axis, v)| {
+            v.checked_abs()
+                .and_then(|v| sum.checked_add(v))
+                .ok_or(SimError::Overflow { body, axis })
+        })
+    }
+
+    fn potential_energy(&self, body: usize) -> Result<i64, SimError> {
+        Sim::abs_sum(&self.position(body), body)
+    }
+
+    fn kinetic_energy(&self, body: usize) -> Result<i64, SimError> {
+        Sim::abs_sum(&self.velocity(body), body)
+    }
+
+    fn energy(&self, body: usize) -> Result<i64, SimError> {
+        self.potential_energy(body)?
+            .checked_mul(self.kinetic_energy(body)?)
+            .ok_or(SimError::EnergyOverflow { body })
+    }
+
+    fn total_energy(&self) -> Result<i64, SimError> {
+        (0..self.body_count()).try_fold(0i64, |total, body| {
+            total
+                .checked_add(self.energy(body)?)
+                .ok_or(SimError::EnergyOverflow { body })
+        })
+    }
+
+    // Steps until this axis returns to where it started. Every state has exactly one
+    // predecessor, so the first repeat is always the starting state.
+    fn find_repeat_component(objects_n: &mut [Component]) -> Result<u64, usize> {
         let mut count = 0u64;
 
-        let initial_state = objects_n.clone();
-        Sim::single_step_component(objects_n);
+        let initial_state = objects_n.to_vec();
+        Sim::single_step_component(objects_n)?;
         count += 1;
 
-        while *objects_n != initial_state {
-            Sim::single_step_component(objects_n);
+        while *objects_n != initial_state[..] {
+            Sim::single_step_component(objects_n)?;
             count += 1;
         }
 
-        count
+        Ok(count)
+    }
+
+    fn axis_periods(&self) -> Result<Vec<u64>, SimError> {
+        self.axes
+            .iter()
+            .enumerate()
+            .map(|(axis, objects_n)| {
+                Sim::find_repeat_component(&mut objects_n.clone())
+                    .map_err(|body| SimError::Overflow { body, axis })
+            })
+            .collect()
+    }
+
+    // Lowest common multiple of all the periods, dividing before multiplying to keep it small
+    fn combine_periods(periods: &[u64]) -> Result<u64, SimError> {
+        periods
+            .iter()
+            .enumerate()
+            .try_fold(1u64, |total, (axis, &period)| {
+                (total / gcd(total, period))
+                    .checked_mul(period)
+                    .ok_or(SimError::PeriodOverflow { axis })
+            })
     }
 
-    fn find_repeat(&mut self) -> u64 {
-        let repeat_x = Sim::find_repeat_component(&mut self.objects.x);
-        let repeat_y = Sim::find_repeat_component(&mut self.objects.y);
-        let repeat_z = Sim::find_repeat_component(&mut self.objects.z);
-        // println!("{} {} {}", repeat_x, repeat_y, repeat_z);
-        lcm(lcm(repeat_x, repeat_y), repeat_z)
+    fn find_repeat(&self) -> Result<u64, SimError> {
+        Sim::combine_periods(&self.axis_periods()?)
+    }
+
+    fn axis_name(axis: usize) -> String {
+        match axis {
+            0 => "x".to_string(),
+            1 => "y".to_string(),
+            2 => "z".to_string(),
+            _ => format!("a{}", axis),
+        }
+    }
+
+    // One row per body per step, from now until `num_steps` later
+    fn trajectory_csv(&mut self, num_steps: u32) -> Result<String, SimError> {
+        let names: Vec<String> = (0..self.axes.len()).map(Sim::axis_name).collect();
+        let velocities: Vec<String> = names.iter().map(|name| format!("v{}", name)).collect();
+        let mut csv = format!(
+            "step,body,{},{},energy\n",
+            names.join(","),
+            velocities.join(",")
+        );
+
+        for step in 0..=num_steps {
+            if step > 0 {
+                self.single_step()?;
+            }
+            for body in 0..self.body_count() {
+                let values: Vec<String> = self
+                    .position(body)
+                    .iter()
+                    .chain(self.velocity(body).iter())
+                    .map(|v| v.to_string())
+                    .collect();
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    step,
+                    body,
+                    values.join(","),
+                    self.energy(body)?
+                ));
+            }
+        }
+        Ok(csv)
     }
 
     fn display(&self) {
-        for i in 0..self.objects.x.len() {
+        for body in 0..self.body_count() {
             println!(
-                "pos: ({}, {}, {}), vel: ({}, {}, {})",
-                self.objects.x[i].position,
-                self.objects.y[i].position,
-                self.objects.z[i].position,
-                self.objects.x[i].velocity,
-                self.objects.y[i].velocity,
-                self.objects.z[i].velocity
+                "pos: {:?}, vel: {:?}",
+                self.position(body),
+                self.velocity(body)
             );
         }
         println!();
     }
 }
 
+// One body per line, with any number of `name=value` coordinates
+fn parse_positions(input: &str) -> Vec<Vec<i64>> {
+    let re = Regex::new(r"\w+=(-?\d+)").unwrap();
+    input
+        .lines()
+        .filter(|line| line.trim().is_empty() == false)
+        .map(|line| {
+            re.captures_iter(line)
+                .map(|cap| cap[1].parse::<i64>().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
 #[aoc(day12, part2)]
 pub fn solve(input: &str) -> u64 {
-    let re = Regex::new(r"<x=([-\d]+), y=([-\d]+), z=([-\d]+)>").unwrap();
-    let positions: Vec<(i32, i32, i32)> = re
-        .captures_iter(input)
-        .map(|cap| {
-            (
-                cap[1].parse::<i32>().unwrap(),
-                cap[2].parse::<i32>().unwrap(),
-                cap[3].parse::<i32>().unwrap(),
-            )
-        })
-        .collect();
-    let mut sim = Sim::from_positions(positions);
+    let sim = match Sim::from_positions(parse_positions(input)) {
+        Ok(sim) => sim,
+        Err(e) => panic!("{}", e),
+    };
 
     sim.display();
-    let repeat_steps = sim.find_repeat();
+    let repeat_steps = match sim.find_repeat() {
+        Ok(repeat_steps) => repeat_steps,
+        Err(e) => panic!("{}", e),
+    };
     println!("Repeat found after {} steps!", repeat_steps);
     repeat_steps
 }
@@ -188,95 +333,185 @@ mod test {
 
     #[test]
     fn test_step() {
-        let positions = vec![(-1, 0, 2), (2, -10, -7), (4, -8, 8), (3, 5, -1)];
-        let mut sim = Sim::from_positions(positions);
-        assert_eq!(sim.objects.x[0].position, -1);
-        assert_eq!(sim.objects.y[0].position, 0);
-        assert_eq!(sim.objects.z[0].position, 2);
-        assert_eq!(sim.objects.x[0].velocity, 0);
-        assert_eq!(sim.objects.y[0].velocity, 0);
-        assert_eq!(sim.objects.z[0].velocity, 0);
-        assert_eq!(sim.objects.x[1].position, 2);
-        assert_eq!(sim.objects.y[1].position, -10);
-        assert_eq!(sim.objects.z[1].position, -7);
-        assert_eq!(sim.objects.x[1].velocity, 0);
-        assert_eq!(sim.objects.y[1].velocity, 0);
-        assert_eq!(sim.objects.z[1].velocity, 0);
-        assert_eq!(sim.objects.x[2].position, 4);
-        assert_eq!(sim.objects.y[2].position, -8);
-        assert_eq!(sim.objects.z[2].position, 8);
-        assert_eq!(sim.objects.x[2].velocity, 0);
-        assert_eq!(sim.objects.y[2].velocity, 0);
-        assert_eq!(sim.objects.z[2].velocity, 0);
-        assert_eq!(sim.objects.x[3].position, 3);
-        assert_eq!(sim.objects.y[3].position, 5);
-        assert_eq!(sim.objects.z[3].position, -1);
-        assert_eq!(sim.objects.x[3].velocity, 0);
-        assert_eq!(sim.objects.y[3].velocity, 0);
-        assert_eq!(sim.objects.z[3].velocity, 0);
-        sim.step(10);
-        assert_eq!(sim.objects.x[0].position, 2);
-        assert_eq!(sim.objects.y[0].position, 1);
-        assert_eq!(sim.objects.z[0].position, -3);
-        assert_eq!(sim.objects.x[0].velocity, -3);
-        assert_eq!(sim.objects.y[0].velocity, -2);
-        assert_eq!(sim.objects.z[0].velocity, 1);
-        assert_eq!(sim.objects.x[1].position, 1);
-        assert_eq!(sim.objects.y[1].position, -8);
-        assert_eq!(sim.objects.z[1].position, 0);
-        assert_eq!(sim.objects.x[1].velocity, -1);
-        assert_eq!(sim.objects.y[1].velocity, 1);
-        assert_eq!(sim.objects.z[1].velocity, 3);
-        assert_eq!(sim.objects.x[2].position, 3);
-        assert_eq!(sim.objects.y[2].position, -6);
-        assert_eq!(sim.objects.z[2].position, 1);
-        assert_eq!(sim.objects.x[2].velocity, 3);
-        assert_eq!(sim.objects.y[2].velocity, 2);
-        assert_eq!(sim.objects.z[2].velocity, -3);
-        assert_eq!(sim.objects.x[3].position, 2);
-        assert_eq!(sim.objects.y[3].position, 0);
-        assert_eq!(sim.objects.z[3].position, 4);
-        assert_eq!(sim.objects.x[3].velocity, 1);
-        assert_eq!(sim.objects.y[3].velocity, -1);
-        assert_eq!(sim.objects.z[3].velocity, -1);
-
-        let positions = vec![(-8, -10, 0), (5, 5, 10), (2, -7, 3), (9, -8, -3)];
-        let mut sim = Sim::from_positions(positions);
-        sim.step(100);
-        assert_eq!(sim.objects.x[0].position, 8);
-        assert_eq!(sim.objects.y[0].position, -12);
-        assert_eq!(sim.objects.z[0].position, -9);
-        assert_eq!(sim.objects.x[0].velocity, -7);
-        assert_eq!(sim.objects.y[0].velocity, 3);
-        assert_eq!(sim.objects.z[0].velocity, 0);
-        assert_eq!(sim.objects.x[1].position, 13);
-        assert_eq!(sim.objects.y[1].position, 16);
-        assert_eq!(sim.objects.z[1].position, -3);
-        assert_eq!(sim.objects.x[1].velocity, 3);
-        assert_eq!(sim.objects.y[1].velocity, -11);
-        assert_eq!(sim.objects.z[1].velocity, -5);
-        assert_eq!(sim.objects.x[2].position, -29);
-        assert_eq!(sim.objects.y[2].position, -11);
-        assert_eq!(sim.objects.z[2].position, -1);
-        assert_eq!(sim.objects.x[2].velocity, -3);
-        assert_eq!(sim.objects.y[2].velocity, 7);
-        assert_eq!(sim.objects.z[2].velocity, 4);
-        assert_eq!(sim.objects.x[3].position, 16);
-        assert_eq!(sim.objects.y[3].position, -13);
-        assert_eq!(sim.objects.z[3].position, 23);
-        assert_eq!(sim.objects.x[3].velocity, 7);
-        assert_eq!(sim.objects.y[3].velocity, 1);
-        assert_eq!(sim.objects.z[3].velocity, 1);
+        let positions = vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ];
+        let mut sim = Sim::from_positions(positions).unwrap();
+        assert_eq!(sim.position(0), vec![-1, 0, 2]);
+        assert_eq!(sim.velocity(0), vec![0, 0, 0]);
+        assert_eq!(sim.position(1), vec![2, -10, -7]);
+        assert_eq!(sim.velocity(1), vec![0, 0, 0]);
+        assert_eq!(sim.position(2), vec![4, -8, 8]);
+        assert_eq!(sim.velocity(2), vec![0, 0, 0]);
+        assert_eq!(sim.position(3), vec![3, 5, -1]);
+        assert_eq!(sim.velocity(3), vec![0, 0, 0]);
+        sim.step(10).unwrap();
+        assert_eq!(sim.position(0), vec![2, 1, -3]);
+        assert_eq!(sim.velocity(0), vec![-3, -2, 1]);
+        assert_eq!(sim.position(1), vec![1, -8, 0]);
+        assert_eq!(sim.velocity(1), vec![-1, 1, 3]);
+        assert_eq!(sim.position(2), vec![3, -6, 1]);
+        assert_eq!(sim.velocity(2), vec![3, 2, -3]);
+        assert_eq!(sim.position(3), vec![2, 0, 4]);
+        assert_eq!(sim.velocity(3), vec![1, -1, -1]);
+
+        let positions = vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ];
+        let mut sim = Sim::from_positions(positions).unwrap();
+        sim.step(100).unwrap();
+        assert_eq!(sim.position(0), vec![8, -12, -9]);
+        assert_eq!(sim.velocity(0), vec![-7, 3, 0]);
+        assert_eq!(sim.position(1), vec![13, 16, -3]);
+        assert_eq!(sim.velocity(1), vec![3, -11, -5]);
+        assert_eq!(sim.position(2), vec![-29, -11, -1]);
+        assert_eq!(sim.velocity(2), vec![-3, 7, 4]);
+        assert_eq!(sim.position(3), vec![16, -13, 23]);
+        assert_eq!(sim.velocity(3), vec![7, 1, 1]);
     }
 
     #[test]
     fn test_find_repeat() {
-        let positions = vec![(-1, 0, 2), (2, -10, -7), (4, -8, 8), (3, 5, -1)];
-        let mut sim = Sim::from_positions(positions);
-        assert_eq!(sim.find_repeat(), 2772);
+        let positions = vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ];
+        let sim = Sim::from_positions(positions).unwrap();
+        assert_eq!(sim.find_repeat(), Ok(2772));
+
+        let positions = vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ];
+        let sim = Sim::from_positions(positions).unwrap();
+        assert_eq!(sim.find_repeat(), Ok(4686774924));
+    }
+
+    #[test]
+    fn test_energy() {
+        let positions = vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ];
+        let mut sim = Sim::from_positions(positions).unwrap();
+        sim.step(10).unwrap();
+        assert_eq!(sim.potential_energy(0), Ok(6));
+        assert_eq!(sim.kinetic_energy(0), Ok(6));
+        assert_eq!(sim.total_energy(), Ok(179));
+
+        let positions = vec![
+            vec![-8, -10, 0],
+            vec![5, 5, 10],
+            vec![2, -7, 3],
+            vec![9, -8, -3],
+        ];
+        let mut sim = Sim::from_positions(positions).unwrap();
+        sim.step(100).unwrap();
+        assert_eq!(sim.total_energy(), Ok(1940));
+    }
+
+    #[test]
+    fn test_axis_periods() {
+        let positions = vec![
+            vec![-1, 0, 2],
+            vec![2, -10, -7],
+            vec![4, -8, 8],
+            vec![3, 5, -1],
+        ];
+        let sim = Sim::from_positions(positions.clone()).unwrap();
+        assert_eq!(sim.axis_periods(), Ok(vec![18, 28, 44]));
+
+        // A fourth axis and a fifth body change nothing along the first three axes
+        let mut positions: Vec<Vec<i64>> = positions
+            .into_iter()
+            .map(|mut p| {
+                p.push(0);
+                p
+            })
+            .collect();
+        positions.push(vec![0, 0, 0, 0]);
+        positions[4][0] = 100;
+        let sim = Sim::from_positions(positions).unwrap();
+        assert_eq!(sim.axis_periods().unwrap()[3], 1);
+        assert_eq!(sim.body_count(), 5);
+    }
 
-        let positions = vec![(-8, -10, 0), (5, 5, 10), (2, -7, 3), (9, -8, -3)];
-        let mut sim = Sim::from_positions(positions);
-        assert_eq!(sim.find_repeat(), 4686774924);
+    #[test]
+    fn test_errors() {
+        assert_eq!(Sim::from_positions(vec![]).err(), Some(SimError::NoBodies));
+        assert_eq!(
+            Sim::from_positions(vec![vec![1, 2], vec![3]]).err(),
+            Some(SimError::DimensionMismatch(1))
+        );
+
+        let mut sim = Sim::from_positions(vec![vec![0, 1], vec![0, 0]]).unwrap();
+        sim.axes[1][1].velocity = i64::MAX;
+        assert_eq!(
+            sim.single_step(),
+            Err(SimError::Overflow { body: 1, axis: 1 })
+        );
+
+        let sim = Sim::from_positions(vec![vec![i64::MIN], vec![0]]).unwrap();
+        assert_eq!(
+            sim.potential_energy(0),
+            Err(SimError::Overflow { body: 0, axis: 0 })
+        );
+
+        // Potential and kinetic energy both fit, their product doesn't
+        let mut sim = Sim::from_positions(vec![vec![0], vec![1 << 32]]).unwrap();
+        sim.axes[0][1].velocity = 1 << 32;
+        assert_eq!(sim.energy(0), Ok(0));
+        assert_eq!(sim.energy(1), Err(SimError::EnergyOverflow { body: 1 }));
+        assert_eq!(
+            sim.energy(1).unwrap_err().to_string(),
+            "Energy overflowed at body 1"
+        );
+
+        // Each body's energy fits, the total doesn't
+        let mut sim = Sim::from_positions(vec![vec![1 << 31], vec![1 << 31]]).unwrap();
+        sim.axes[0][0].velocity = 1 << 31;
+        sim.axes[0][1].velocity = 1 << 31;
+        assert_eq!(sim.energy(1), Ok(1 << 62));
+        assert_eq!(
+            sim.total_energy(),
+            Err(SimError::EnergyOverflow { body: 1 })
+        );
+
+        assert_eq!(Sim::combine_periods(&[18, 28, 44]), Ok(2772));
+        assert_eq!(Sim::combine_periods(&[u64::MAX, u64::MAX]), Ok(u64::MAX));
+        assert_eq!(
+            Sim::combine_periods(&[1 << 40, 3, 1 << 20, (1 << 30) - 1]),
+            Err(SimError::PeriodOverflow { axis: 3 })
+        );
+    }
+
+    #[test]
+    fn test_trajectory_csv() {
+        let mut sim = Sim::from_positions(parse_positions("<x=0>\n<x=3>\n")).unwrap();
+        assert_eq!(
+            sim.trajectory_csv(2),
+            Ok("\
+step,body,x,vx,energy
+0,0,0,0,0
+0,1,3,0,0
+1,0,1,1,1
+1,1,2,-1,2
+2,0,3,2,6
+2,1,0,-2,0
+"
+            .to_string())
+        );
     }
 }
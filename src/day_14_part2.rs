@@ -10,7 +10,8 @@
     Given 1 trillion ORE, what is the maximum amount of FUEL you can produce?
 */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 struct Chemical {
@@ -19,14 +20,13 @@ struct Chemical {
 }
 
 impl Chemical {
-    fn from_string(input: &str, name_map: &mut NameMap) -> Self {
+    fn parse(cursor: &mut Cursor, name_map: &mut NameMap) -> Result<Self, RecipeError> {
         // Parses string in format: "7 A"
-        let mut parts = input.trim().split(' ');
-        let num = parts.next().unwrap().parse::<i64>().unwrap();
-        let name = parts.next().unwrap();
-        let id = name_map.get_id(name);
+        let num = cursor.quantity()?;
+        let name = cursor.name()?;
+        let id = name_map.get_id(&name);
 
-        Self { id, num }
+        Ok(Self { id, num })
     }
 }
 
@@ -37,36 +37,136 @@ struct Reaction {
 }
 
 impl Reaction {
-    fn from_string(input: &str, name_map: &mut NameMap) -> Self {
-        let pieces: Vec<&str> = input.split("=>").collect();
-        let from_chemicals: Vec<Chemical> = pieces[0]
-            .split(',')
-            .map(|s| Chemical::from_string(s, name_map))
-            .collect();
-        let to_chemical = Chemical::from_string(pieces[1], name_map);
+    fn from_string(input: &str, line: usize, name_map: &mut NameMap) -> Result<Self, RecipeError> {
+        // Parses string in format: "7 A, 1 B => 1 C"
+        let mut cursor = Cursor::new(input, line);
+        let mut from_chemicals = vec![Chemical::parse(&mut cursor, name_map)?];
+        while cursor.accept(",") == true {
+            from_chemicals.push(Chemical::parse(&mut cursor, name_map)?);
+        }
+        if cursor.accept("=>") == false {
+            return Err(cursor.expected("',' or '=>'"));
+        }
+        let to_chemical = Chemical::parse(&mut cursor, name_map)?;
+        cursor.end()?;
 
-        Self {
+        Ok(Self {
             from: from_chemicals,
             to: to_chemical,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RecipeError {
+    // Line and column (both from 1) followed by what should have been there
+    Expected(usize, usize, &'static str),
+    BadQuantity(usize, usize, String),
+    ZeroQuantity(usize, usize),
+    // Line of the second reaction, the chemical, and the line of the first reaction
+    DuplicateProducer(usize, String, usize),
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeError::Expected(line, column, what) => {
+                write!(f, "Line {}, column {}: expected {}", line, column, what)
+            }
+            RecipeError::BadQuantity(line, column, text) => {
+                write!(f, "Line {}, column {}: bad quantity {}", line, column, text)
+            }
+            RecipeError::ZeroQuantity(line, column) => {
+                write!(f, "Line {}, column {}: quantity can't be 0", line, column)
+            }
+            RecipeError::DuplicateProducer(line, name, first) => write!(
+                f,
+                "Line {}: {} is already produced on line {}",
+                line, name, first
+            ),
+        }
+    }
+}
+
+// Walks through one line of a recipe, skipping spaces between the pieces
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn new(input: &str, line: usize) -> Self {
+        Self {
+            chars: input.chars().collect(),
+            pos: 0,
+            line,
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() == true {
+            self.pos += 1;
+        }
+    }
+
+    fn expected(&self, what: &'static str) -> RecipeError {
+        RecipeError::Expected(self.line, self.pos + 1, what)
+    }
+
+    fn take_while(&mut self, matcher: impl Fn(char) -> bool) -> String {
+        self.skip_spaces();
+        let start = self.pos;
+        while self.pos < self.chars.len() && matcher(self.chars[self.pos]) == true {
+            self.pos += 1;
         }
+        self.chars[start..self.pos].iter().collect()
     }
 
-    fn react(&self, supply: &mut HashMap<u32, i64>, count: i64) {
-        for consumed_chemical in &self.from {
-            let chemical_name = consumed_chemical.id;
-            let entry = supply.entry(chemical_name).or_insert(0);
-            *entry -= consumed_chemical.num * count;
+    fn accept(&mut self, token: &str) -> bool {
+        self.skip_spaces();
+        let token: Vec<char> = token.chars().collect();
+        if self.chars[self.pos..].starts_with(&token) == true {
+            self.pos += token.len();
+            true
+        } else {
+            false
         }
+    }
 
-        let produced_chemical = &self.to;
-        let chemical_name = produced_chemical.id;
-        let entry = supply.entry(chemical_name).or_insert(0);
-        *entry += produced_chemical.num * count;
+    fn quantity(&mut self) -> Result<i64, RecipeError> {
+        let text = self.take_while(|c| c.is_ascii_digit());
+        let column = self.pos - text.len() + 1;
+        if text.is_empty() == true {
+            return Err(self.expected("a quantity"));
+        }
+        match text.parse::<i64>() {
+            Ok(0) => Err(RecipeError::ZeroQuantity(self.line, column)),
+            Ok(num) => Ok(num),
+            Err(_) => Err(RecipeError::BadQuantity(self.line, column, text)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, RecipeError> {
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        if name.is_empty() == true {
+            return Err(self.expected("a chemical name"));
+        }
+        Ok(name)
+    }
+
+    fn end(&mut self) -> Result<(), RecipeError> {
+        self.skip_spaces();
+        if self.pos < self.chars.len() {
+            return Err(self.expected("end of line"));
+        }
+        Ok(())
     }
 }
 
 struct NameMap {
     map: HashMap<String, u32>,
+    names: Vec<String>,
     next_id: u32,
 }
 
@@ -74,10 +174,19 @@ impl NameMap {
     fn new() -> Self {
         Self {
             map: HashMap::new(),
+            names: Vec::new(),
             next_id: 0,
         }
     }
 
+    fn find(&self, input: &str) -> Option<u32> {
+        self.map.get(input).cloned()
+    }
+
+    fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
     fn get_id(&mut self, input: &str) -> u32 {
         let input_owned = input.to_owned();
         let id = self.map.get(&input_owned);
@@ -86,92 +195,209 @@ impl NameMap {
         } else {
             let next_id = self.next_id;
             self.next_id += 1;
+            self.names.push(input_owned.clone());
             self.map.insert(input_owned, next_id);
             next_id
         }
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum PlanError {
+    // Nothing produces the chemical and it isn't one of the raw inputs
+    Unreachable(String),
+    // Chemicals that each need the next one to be made, ending back at the first
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::Unreachable(name) => {
+                write!(f, "No reaction produces {} and it isn't a raw input", name)
+            }
+            PlanError::Cycle(names) => write!(f, "Reactions form a cycle: {}", names.join(" <- ")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Plan {
+    // Produced chemicals in an order their reactions can run, ingredients first
+    order: Vec<String>,
+    runs: HashMap<String, i64>,
+    surplus: HashMap<String, i64>,
+    raw: HashMap<String, i64>,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut raw: Vec<(&String, &i64)> = self.raw.iter().collect();
+        raw.sort();
+        for (name, num) in raw {
+            writeln!(f, "Use {} {}", num, name)?;
+        }
+        for name in &self.order {
+            let runs = self.runs[name];
+            write!(
+                f,
+                "Run {} {} time{}",
+                name,
+                runs,
+                if runs == 1 { "" } else { "s" }
+            )?;
+            match self.surplus.get(name) {
+                Some(spare) => writeln!(f, ", {} left over", spare)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
 struct RecipeBook {
     reactions: HashMap<u32, Reaction>,
     name_map: NameMap,
+    // Produced chemicals in the order their reactions were listed
+    order: Vec<u32>,
 }
 
 impl RecipeBook {
-    fn from_string(input: &str) -> Self {
+    fn from_string(input: &str) -> Result<Self, RecipeError> {
         let mut name_map = NameMap::new();
-        let reactions: HashMap<u32, Reaction> = input
-            .trim()
-            .lines()
-            .map(|line| {
-                let reaction = Reaction::from_string(line, &mut name_map);
-                (reaction.to.id, reaction)
-            })
-            .collect();
-        Self {
+        let mut reactions = HashMap::new();
+        let mut order = Vec::new();
+        let mut lines: HashMap<u32, usize> = HashMap::new();
+        for (i, line) in input.lines().enumerate() {
+            if line.trim().is_empty() == true {
+                continue;
+            }
+            let reaction = Reaction::from_string(line, i + 1, &mut name_map)?;
+            let id = reaction.to.id;
+            if let Some(&first) = lines.get(&id) {
+                let name = name_map.name(id).to_string();
+                return Err(RecipeError::DuplicateProducer(i + 1, name, first));
+            }
+            lines.insert(id, i + 1);
+            order.push(id);
+            reactions.insert(id, reaction);
+        }
+
+        Ok(Self {
             reactions,
             name_map,
-        }
+            order,
+        })
     }
 
-    fn produce_chemical(&self, supply: &mut HashMap<u32, i64>, chemical: &Chemical) {
-        // Find reaction which produces chemical
-        let reaction = &self.reactions[&chemical.id];
-
-        // Calculate how many times the reaction needs to occur to produce the desired number of chemical
-        let mut react_count = chemical.num / reaction.to.num;
-        if chemical.num % reaction.to.num != 0 {
-            react_count += 1;
-        }
-
-        // Perform reaction X times
-        reaction.react(supply, react_count);
+    fn chemical_to_string(&self, chemical: &Chemical) -> String {
+        format!("{} {}", chemical.num, self.name_map.name(chemical.id))
     }
 
-    fn resolve_debt(&mut self, supply: &mut HashMap<u32, i64>) {
-        loop {
-            // Get all elements in the supply with a negative number (chemical debt)
-            let ore_id = self.name_map.get_id("ORE");
-            let chemical_debts: Vec<Chemical> = supply
+    // Depth first from the goal, adding each chemical after everything it's made from. Meeting
+    // a chemical that's still on the path means the recipes go round in a circle.
+    fn visit(
+        &self,
+        id: u32,
+        raw: &HashSet<u32>,
+        path: &mut Vec<u32>,
+        done: &mut HashSet<u32>,
+        order: &mut Vec<u32>,
+    ) -> Result<(), PlanError> {
+        if raw.contains(&id) == true || done.contains(&id) == true {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|&p| p == id) {
+            let mut cycle: Vec<String> = path[start..]
                 .iter()
-                .filter(|&(&k, &v)| (v < 0) && (k != ore_id))
-                .map(|(&k, &v)| Chemical {
-                    id: k,
-                    num: -v, // Negate to get the amount needed to produce
-                })
+                .map(|&p| self.name_map.name(p).to_string())
                 .collect();
-            if chemical_debts.is_empty() == true {
-                // No more chemical debt, return
-                break;
-            }
-
-            chemical_debts
-                .iter()
-                .for_each(|chemical| self.produce_chemical(supply, chemical));
+            cycle.push(self.name_map.name(id).to_string());
+            return Err(PlanError::Cycle(cycle));
         }
-    }
+        let reaction = match self.reactions.get(&id) {
+            Some(reaction) => reaction,
+            None => return Err(PlanError::Unreachable(self.name_map.name(id).to_string())),
+        };
 
-    fn calculate_ore_for_fuel(&mut self, num_fuel: i64) -> i64 {
-        let mut supply: HashMap<u32, i64> = HashMap::new();
+        path.push(id);
+        for chemical in &reaction.from {
+            self.visit(chemical.id, raw, path, done, order)?;
+        }
+        path.pop();
 
-        let ore_id = self.name_map.get_id("ORE");
-        let fuel_id = self.name_map.get_id("FUEL");
+        done.insert(id);
+        order.push(id);
+        Ok(())
+    }
 
-        let fuel = Chemical {
-            id: fuel_id,
-            num: num_fuel,
+    fn plan(&self, goal: &str, amount: i64, raw: &[&str]) -> Result<Plan, PlanError> {
+        let goal_id = match self.name_map.find(goal) {
+            Some(id) => id,
+            None => return Err(PlanError::Unreachable(goal.to_string())),
         };
-        self.produce_chemical(&mut supply, &fuel);
-        self.resolve_debt(&mut supply);
+        let raw_ids: HashSet<u32> = raw.iter().filter_map(|r| self.name_map.find(r)).collect();
+
+        let mut order = Vec::new();
+        self.visit(
+            goal_id,
+            &raw_ids,
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut order,
+        )?;
+
+        // Work back from the goal so every chemical knows its full demand before its reaction runs
+        let mut needed: HashMap<u32, i64> = HashMap::new();
+        needed.insert(goal_id, amount);
+        let mut runs = HashMap::new();
+        let mut surplus = HashMap::new();
+        for &id in order.iter().rev() {
+            let reaction = &self.reactions[&id];
+            let need = needed.get(&id).cloned().unwrap_or(0);
+
+            // Calculate how many times the reaction needs to occur to produce the desired number of chemical
+            let mut react_count = need / reaction.to.num;
+            if need % reaction.to.num != 0 {
+                react_count += 1;
+            }
+
+            for chemical in &reaction.from {
+                *needed.entry(chemical.id).or_insert(0) += chemical.num * react_count;
+            }
+            let name = self.name_map.name(id).to_string();
+            let spare = react_count * reaction.to.num - need;
+            if spare > 0 {
+                surplus.insert(name.clone(), spare);
+            }
+            runs.insert(name, react_count);
+        }
 
-        // println!();
-        // println!("Final supply: {:?}", supply);
+        let raw = needed
+            .iter()
+            .filter(|(id, _)| raw_ids.contains(id) == true)
+            .map(|(&id, &num)| (self.name_map.name(id).to_string(), num))
+            .collect();
+        Ok(Plan {
+            order: order
+                .iter()
+                .map(|&id| self.name_map.name(id).to_string())
+                .collect(),
+            runs,
+            surplus,
+            raw,
+        })
+    }
 
-        let ore_required = supply.get(&ore_id).unwrap_or(&0i64) * -1;
-        ore_required
+    fn calculate_ore_for_fuel(&self, num_fuel: i64) -> i64 {
+        let plan = match self.plan("FUEL", num_fuel, &["ORE"]) {
+            Ok(plan) => plan,
+            Err(e) => panic!("{}", e),
+        };
+        plan.raw.get("ORE").cloned().unwrap_or(0)
     }
 
-    fn calculate_max_fuel(&mut self, ore_to_use: i64) -> i64 {
+    fn calculate_max_fuel(&self, ore_to_use: i64) -> i64 {
         let mut last_good = 0;
         let mut attempt = 1;
 
@@ -210,9 +436,32 @@ impl RecipeBook {
     }
 }
 
+impl fmt::Display for RecipeBook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in &self.order {
+            let reaction = &self.reactions[id];
+            let from: Vec<String> = reaction
+                .from
+                .iter()
+                .map(|c| self.chemical_to_string(c))
+                .collect();
+            writeln!(
+                f,
+                "{} => {}",
+                from.join(", "),
+                self.chemical_to_string(&reaction.to)
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[aoc(day14, part2)]
 pub fn solve(input: &str) -> i64 {
-    let mut recipe_book = RecipeBook::from_string(input);
+    let recipe_book = match RecipeBook::from_string(input) {
+        Ok(recipe_book) => recipe_book,
+        Err(e) => panic!("{}", e),
+    };
     let max_fuel = recipe_book.calculate_max_fuel(1_000_000_000_000);
     println!("Max fuel: {}", max_fuel);
     max_fuel
@@ -235,7 +484,7 @@ mod test {
 165 ORE => 2 GPVTF
 3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT
 ";
-        let mut recipe_book = RecipeBook::from_string(input);
+        let recipe_book = RecipeBook::from_string(input).unwrap();
         let max_fuel = recipe_book.calculate_max_fuel(1_000_000_000_000);
         assert_eq!(max_fuel, 82892753);
 
@@ -253,7 +502,7 @@ mod test {
 1 VJHF, 6 MNCFX => 4 RFSQX
 176 ORE => 6 VJHF
 ";
-        let mut recipe_book = RecipeBook::from_string(input);
+        let recipe_book = RecipeBook::from_string(input).unwrap();
         let max_fuel = recipe_book.calculate_max_fuel(1_000_000_000_000);
         assert_eq!(max_fuel, 5586022);
 
@@ -276,8 +525,95 @@ mod test {
 7 XCVML => 6 RJRHP
 5 BHXH, 4 VRPVC => 5 LTCX
 ";
-        let mut recipe_book = RecipeBook::from_string(input);
+        let recipe_book = RecipeBook::from_string(input).unwrap();
         let max_fuel = recipe_book.calculate_max_fuel(1_000_000_000_000);
         assert_eq!(max_fuel, 460664);
     }
+
+    #[test]
+    fn test_plan() {
+        let input = "
+10 ORE => 10 A
+1 ORE => 1 B
+7 A, 1 B => 1 C
+7 A, 1 C => 1 D
+7 A, 1 D => 1 E
+7 A, 1 E => 1 FUEL
+";
+        let recipe_book = RecipeBook::from_string(input).unwrap();
+        let plan = recipe_book.plan("FUEL", 1, &["ORE"]).unwrap();
+        assert_eq!(plan.order, vec!["A", "B", "C", "D", "E", "FUEL"]);
+        assert_eq!(plan.runs["A"], 3);
+        assert_eq!(plan.runs["FUEL"], 1);
+        assert_eq!(
+            plan.surplus,
+            [("A".to_string(), 2)].iter().cloned().collect()
+        );
+        assert_eq!(plan.raw["ORE"], 31);
+        assert_eq!(
+            plan.to_string(),
+            "Use 31 ORE
+Run A 3 times, 2 left over
+Run B 1 time
+Run C 1 time
+Run D 1 time
+Run E 1 time
+Run FUEL 1 time
+"
+        );
+
+        // Treat B as a raw input and aim for something other than FUEL
+        let plan = recipe_book.plan("D", 2, &["ORE", "B"]).unwrap();
+        assert_eq!(plan.order, vec!["A", "C", "D"]);
+        assert_eq!(plan.raw["ORE"], 30);
+        assert_eq!(plan.raw["B"], 2);
+        assert_eq!(plan.runs.get("B"), None);
+
+        let plan = recipe_book.plan("ORE", 5, &["ORE"]).unwrap();
+        assert_eq!(plan.order, Vec::<String>::new());
+        assert_eq!(plan.raw["ORE"], 5);
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        let input = "
+1 ORE, 1 C => 1 A
+1 A => 1 B
+1 B => 1 C
+1 A => 1 FUEL
+";
+        let recipe_book = RecipeBook::from_string(input).unwrap();
+        let error = recipe_book.plan("FUEL", 1, &["ORE"]).unwrap_err();
+        assert_eq!(
+            error,
+            PlanError::Cycle(vec![
+                "A".to_string(),
+                "C".to_string(),
+                "B".to_string(),
+                "A".to_string()
+            ])
+        );
+        assert_eq!(
+            error.to_string(),
+            "Reactions form a cycle: A <- C <- B <- A"
+        );
+
+        let input = "
+1 ORE, 2 X => 1 FUEL
+";
+        let recipe_book = RecipeBook::from_string(input).unwrap();
+        assert_eq!(
+            recipe_book.plan("FUEL", 1, &["ORE"]),
+            Err(PlanError::Unreachable("X".to_string()))
+        );
+        assert!(recipe_book.plan("FUEL", 1, &["ORE", "X"]).is_ok());
+        assert_eq!(
+            recipe_book.plan("FUEL", 1, &["X"]),
+            Err(PlanError::Unreachable("ORE".to_string()))
+        );
+        assert_eq!(
+            recipe_book.plan("GOLD", 1, &["ORE"]),
+            Err(PlanError::Unreachable("GOLD".to_string()))
+        );
+    }
 }
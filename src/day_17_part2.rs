@@ -41,7 +41,7 @@
     Run your ASCII program. What is the sum of the alignment parameters for the scaffold intersections?
 */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 struct Program {
@@ -506,65 +506,28 @@ impl Camera {
         println!();
     }
 
-    fn find_path(&self) -> Vec<Segment> {
-        // First find starting point
-        let mut starting_coord = None;
-        let mut starting_dir = None;
+    fn is_scaffold(&self, coord: (i32, i32)) -> bool {
+        matches!(
+            self.area.get(&coord),
+            Some(Space::Scaffold) | Some(Space::Intersection) | Some(Space::Robot(_))
+        )
+    }
+
+    fn find_robot(&self) -> ((i32, i32), Cardinal) {
         for (&k, &v) in self.area.iter() {
             if let Space::Robot(dir) = v {
-                starting_coord = Some(k);
-                starting_dir = Some(dir);
+                return (k, dir);
             }
         }
+        panic!("Error: could not find starting point");
+    }
 
-        if starting_coord == None || starting_dir == None {
-            panic!("Error: could not find starting point");
-        }
-
-        // Calculate path using naieve / greedy movement
-        let mut path: Vec<Segment> = Vec::new();
-        let mut current_coord = starting_coord.unwrap();
-        let mut current_dir = starting_dir.unwrap();
-        loop {
-            // Get new direction
-            let mut next_turn = None;
-            for &t in [Turn::Left, Turn::Right].iter() {
-                // Turn in the candidate direction, then take a step
-                let step_dir = current_dir.turn(t);
-                let step_coord = step_dir.step_from(current_coord);
-                if self.area.get(&step_coord) == Some(&Space::Scaffold) {
-                    next_turn = Some(t);
-                }
-            }
-
-            if next_turn == None {
-                // No more segments to follow
-                break;
-            }
-            let next_turn = next_turn.unwrap();
-            current_dir = current_dir.turn(next_turn);
-
-            // Get distance
-            let mut distance = 0;
-            loop {
-                let step_coord = current_dir.step_from(current_coord);
-                if self.area.get(&step_coord) == Some(&Space::Scaffold) {
-                    current_coord = step_coord;
-                    distance += 1;
-                } else {
-                    break;
-                }
-            }
-
-            // Segment is complete
-            let segment = Segment {
-                turn: next_turn,
-                distance,
-            };
-            path.push(segment);
-        }
-
-        path
+    // Candidate paths are given to `visit` one at a time until it accepts one
+    fn plan_paths<T, F>(&self, mut visit: F) -> Option<T>
+    where
+        F: FnMut(&[Move]) -> Option<T>,
+    {
+        PathPlanner::new(self).plan(&mut visit)
     }
 
     fn give_string(&mut self, string: &str) {
@@ -574,50 +537,26 @@ impl Camera {
         self.program.input.push(0x0A_i64); // Always end with newline
     }
 
-    fn give_main_routine(&mut self, main_routine: &[usize]) {
-        let main_routine_str: String = main_routine
-            .iter()
-            .map(|x| match x {
-                0 => 'A',
-                1 => 'B',
-                2 => 'C',
-                _ => 'X',
-            })
-            .collect();
+    fn give_main_routine(&mut self, encoding: &Encoding) {
+        let main_routine_str = encoding.main_routine_string();
         //println!("Main routine: {}", main_routine_str);
 
         self.give_string(&main_routine_str);
     }
 
-    fn give_sub_routines(&mut self, sub_routines: &[String]) {
-        for routine in sub_routines {
-            // Routines are in a packed string format. Need to split and insert commas.
-            let mut expect_alpha = true;
-            let mut sub_routine_vec: Vec<String> = Vec::new();
-            let mut tmp_vec: Vec<char> = Vec::new();
-            for c in routine.chars() {
-                let complete = expect_alpha == c.is_digit(10);
-                if complete == true {
-                    let value_string = tmp_vec.iter().collect::<String>();
-                    tmp_vec.clear();
-
-                    sub_routine_vec.push(value_string);
-
-                    expect_alpha = !expect_alpha;
-                }
-                tmp_vec.push(c);
-            }
-
-            // If there's anything leftover at the end (should be), push it too.
-            if tmp_vec.is_empty() == false {
-                let value_string = tmp_vec.iter().collect::<String>();
-                tmp_vec.clear();
+    fn give_sub_routines(&mut self, encoding: &Encoding, count: usize) {
+        let mut sub_routine_strs = encoding.sub_routine_strings();
+        if sub_routine_strs.len() > count {
+            panic!("Too many sub routines: {}", sub_routine_strs.len());
+        }
 
-                sub_routine_vec.push(value_string);
-            }
+        // The robot always asks for every routine, even the ones that are never called. Fill with a copy of the last one.
+        while sub_routine_strs.len() < count {
+            let filler = sub_routine_strs.last().cloned().unwrap_or_default();
+            sub_routine_strs.push(filler);
+        }
 
-            // Make string and give to robot
-            let sub_routine_str = sub_routine_vec.join(",");
+        for sub_routine_str in sub_routine_strs {
             //println!("Sub routine: {}", sub_routine_str);
             self.give_string(&sub_routine_str);
         }
@@ -650,107 +589,449 @@ impl Camera {
     }
 }
 
-fn segment_to_string(segment: &Segment, with_comma: bool) -> String {
-    let parts = [segment.turn.to_string(), segment.distance.to_string()];
-    parts.join(if with_comma == true { "," } else { "" })
+const ROUTINE_COUNT: usize = 3;
+const ROUTINE_LIMIT: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Move {
+    Turn(Turn),
+    Forward(i32),
 }
 
-fn path_to_string(path: &[Segment], with_comma: bool) -> String {
-    let path_string = path
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Turn(turn) => write!(f, "{}", turn),
+            Self::Forward(distance) => write!(f, "{}", distance),
+        }
+    }
+}
+
+fn path_to_moves(path: &[Segment]) -> Vec<Move> {
+    path.iter()
+        .flat_map(|seg| vec![Move::Turn(seg.turn), Move::Forward(seg.distance)])
+        .collect()
+}
+
+fn moves_to_string(moves: &[Move]) -> String {
+    moves
         .iter()
-        .map(|seg| segment_to_string(seg, with_comma))
+        .map(|m| m.to_string())
         .collect::<Vec<String>>()
-        .join(if with_comma == true { "," } else { "" });
-    // println!("Path: {}", path_string);
-    path_string
+        .join(",")
 }
 
-fn split_sub_routines(path: &[Segment]) -> Vec<String> {
-    let mut histogram: HashMap<String, i32> = HashMap::new();
-    for size in 2..6 {
-        // Impossible to have more than 5 segments in 20 characters ("X,N," * 5)
-        for i in 0..(path.len() - size) {
-            let sub_path = &path[i..(i + size)];
-            let sub_path_str = path_to_string(sub_path, false);
-            if sub_path_str.len() <= 20 {
-                // Each sub routine is limited on length
-                let entry = histogram.entry(sub_path_str).or_insert(0);
-                *entry += 1;
-            }
+// Consecutive forward moves are the same as one long forward move
+fn merge_forwards(moves: &[Move]) -> Vec<Move> {
+    let mut merged: Vec<Move> = Vec::new();
+    for &m in moves {
+        match (merged.last_mut(), m) {
+            (Some(Move::Forward(total)), Move::Forward(distance)) => *total += distance,
+            _ => merged.push(m),
         }
     }
-    // println!("Histogram: {:#?}", histogram);
+    merged
+}
 
-    // Convert the hashmap to a vector
-    let mut histogram_vec = histogram
-        .iter()
-        .map(|(k, v)| (k.clone(), *v))
-        .collect::<Vec<(String, i32)>>();
-    histogram_vec.sort_by(|a, b| a.1.cmp(&b.1).reverse());
-    // println!("Histogram vec: {:#?}", histogram_vec);
-    let paths_vec = histogram_vec
-        .iter()
-        .map(|(string, _)| string.clone())
-        .collect::<Vec<String>>();
-    // println!("Paths vec: {:#?}", paths_vec);
-    paths_vec
+#[derive(Clone, Debug, PartialEq)]
+struct Encoding {
+    main_routine: Vec<usize>,
+    sub_routines: Vec<Vec<Move>>,
 }
 
-fn match_path(
-    path_str: &str,
-    candidates: &[String],
-    main_routine: &mut Vec<usize>,
-    sub_routines: &mut Vec<String>,
-) -> bool {
-    for c in candidates {
-        if c.len() <= path_str.len() && c == &path_str[0..c.len()] {
-            let mut routine_pushed = false;
-            if sub_routines.contains(c) == false {
-                if sub_routines.len() < 3 {
-                    sub_routines.push(c.clone());
-                    routine_pushed = true;
-                } else {
-                    // We already had 3 sub routines in use and this candidate wasn't one of them. Move along.
-                    continue;
-                }
+impl Encoding {
+    fn routine_name(idx: usize) -> char {
+        (b'A' + idx as u8) as char
+    }
+
+    fn main_routine_string(&self) -> String {
+        self.main_routine
+            .iter()
+            .map(|&idx| Self::routine_name(idx).to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+
+    fn sub_routine_strings(&self) -> Vec<String> {
+        self.sub_routines
+            .iter()
+            .map(|routine| moves_to_string(routine))
+            .collect()
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.main_routine_string().len()
+            + self
+                .sub_routine_strings()
+                .iter()
+                .map(|s| s.len())
+                .sum::<usize>()
+    }
+
+    fn expand(&self) -> Vec<Move> {
+        let moves: Vec<Move> = self
+            .main_routine
+            .iter()
+            .flat_map(|&idx| self.sub_routines[idx].iter().copied())
+            .collect();
+        merge_forwards(&moves)
+    }
+}
+
+// A position within the path. If splitting is allowed, part of a forward move may already be consumed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cursor {
+    idx: usize,
+    consumed: i32,
+}
+
+impl Cursor {
+    fn next(&self) -> Self {
+        Self {
+            idx: self.idx + 1,
+            consumed: 0,
+        }
+    }
+}
+
+struct Compressor {
+    sub_routine_count: usize,
+    sub_routine_limit: usize,
+    main_routine_limit: usize,
+    split_forward: bool,
+}
+
+impl Compressor {
+    fn new(
+        sub_routine_count: usize,
+        sub_routine_limit: usize,
+        main_routine_limit: usize,
+        split_forward: bool,
+    ) -> Self {
+        if sub_routine_count > 26 {
+            panic!("Too many sub routines to name: {}", sub_routine_count);
+        }
+
+        Self {
+            sub_routine_count,
+            sub_routine_limit,
+            main_routine_limit,
+            split_forward,
+        }
+    }
+
+    fn max_main_routine_calls(&self) -> usize {
+        self.main_routine_limit.div_ceil(2) // "A,B,C" -- one character per call plus commas
+    }
+
+    fn all_encodings(&self, path: &[Move]) -> Vec<Encoding> {
+        let path = merge_forwards(path);
+        let mut main_routine: Vec<usize> = Vec::new();
+        let mut sub_routines: Vec<Vec<Move>> = Vec::new();
+        let mut found: Vec<Encoding> = Vec::new();
+        let start = Cursor {
+            idx: 0,
+            consumed: 0,
+        };
+        self.search(
+            &path,
+            start,
+            &mut main_routine,
+            &mut sub_routines,
+            &mut found,
+        );
+        found
+    }
+
+    // Like `all_encodings`, but gives up on any branch that can't beat the shortest encoding found so far
+    fn best_encoding(&self, path: &[Move]) -> Option<Encoding> {
+        let path = merge_forwards(path);
+        let mut main_routine: Vec<usize> = Vec::new();
+        let mut sub_routines: Vec<Vec<Move>> = Vec::new();
+        let mut best: Option<Encoding> = None;
+        let start = Cursor {
+            idx: 0,
+            consumed: 0,
+        };
+        self.search_best(
+            &path,
+            start,
+            &mut main_routine,
+            &mut sub_routines,
+            &mut best,
+        );
+        best
+    }
+
+    // The encoded length so far. Calls and routines are only ever added, so no completion can be shorter.
+    fn partial_len(main_routine: &[usize], sub_routines: &[Vec<Move>]) -> usize {
+        (2 * main_routine.len()).saturating_sub(1)
+            + sub_routines
+                .iter()
+                .map(|routine| moves_to_string(routine).len())
+                .sum::<usize>()
+    }
+
+    fn search(
+        &self,
+        path: &[Move],
+        cursor: Cursor,
+        main_routine: &mut Vec<usize>,
+        sub_routines: &mut Vec<Vec<Move>>,
+        found: &mut Vec<Encoding>,
+    ) {
+        if cursor.idx == path.len() {
+            found.push(Encoding {
+                main_routine: main_routine.clone(),
+                sub_routines: sub_routines.clone(),
+            });
+            return;
+        }
+
+        if main_routine.len() >= self.max_main_routine_calls() {
+            return;
+        }
+
+        // Try each routine that's already defined
+        for idx in 0..sub_routines.len() {
+            if let Some(next) = self.match_routine(path, cursor, &sub_routines[idx]) {
+                main_routine.push(idx);
+                self.search(path, next, main_routine, sub_routines, found);
+                main_routine.pop();
             }
+        }
 
-            let position = sub_routines.iter().position(|i| i == c).unwrap();
-            main_routine.push(position);
+        // Try defining a new routine that starts here
+        if sub_routines.len() < self.sub_routine_count {
+            for (routine, next) in self.candidate_routines(path, cursor) {
+                if sub_routines.contains(&routine) == true {
+                    continue; // Already covered above
+                }
 
-            // Check if the full path has been matched.
-            if c.len() == path_str.len() {
-                return true;
+                main_routine.push(sub_routines.len());
+                sub_routines.push(routine);
+                self.search(path, next, main_routine, sub_routines, found);
+                sub_routines.pop();
+                main_routine.pop();
             }
+        }
+    }
 
-            let result = match_path(&path_str[c.len()..], candidates, main_routine, sub_routines);
-            if result == true {
-                return true; // Propagate a full match
+    fn search_best(
+        &self,
+        path: &[Move],
+        cursor: Cursor,
+        main_routine: &mut Vec<usize>,
+        sub_routines: &mut Vec<Vec<Move>>,
+        best: &mut Option<Encoding>,
+    ) {
+        if let Some(encoding) = best {
+            if Self::partial_len(main_routine, sub_routines) >= encoding.encoded_len() {
+                return;
             }
-            // It didn't work out. That's fine, we probably have other candidates to try.
+        }
 
-            main_routine.pop();
-            if routine_pushed == true {
+        if cursor.idx == path.len() {
+            *best = Some(Encoding {
+                main_routine: main_routine.clone(),
+                sub_routines: sub_routines.clone(),
+            });
+            return;
+        }
+
+        if main_routine.len() >= self.max_main_routine_calls() {
+            return;
+        }
+
+        // Try each routine that's already defined
+        for idx in 0..sub_routines.len() {
+            if let Some(next) = self.match_routine(path, cursor, &sub_routines[idx]) {
+                main_routine.push(idx);
+                self.search_best(path, next, main_routine, sub_routines, best);
+                main_routine.pop();
+            }
+        }
+
+        // Try defining a new routine that starts here
+        if sub_routines.len() < self.sub_routine_count {
+            for (routine, next) in self.candidate_routines(path, cursor) {
+                if sub_routines.contains(&routine) == true {
+                    continue; // Already covered above
+                }
+
+                main_routine.push(sub_routines.len());
+                sub_routines.push(routine);
+                self.search_best(path, next, main_routine, sub_routines, best);
                 sub_routines.pop();
+                main_routine.pop();
+            }
+        }
+    }
+
+    fn match_routine(&self, path: &[Move], cursor: Cursor, routine: &[Move]) -> Option<Cursor> {
+        let mut cursor = cursor;
+        for (i, &m) in routine.iter().enumerate() {
+            let is_last = i == routine.len() - 1;
+            match (m, *path.get(cursor.idx)?) {
+                (Move::Turn(turn), Move::Turn(path_turn))
+                    if turn == path_turn && cursor.consumed == 0 =>
+                {
+                    cursor = cursor.next();
+                }
+                (Move::Forward(distance), Move::Forward(path_distance)) => {
+                    let remaining = path_distance - cursor.consumed;
+                    if distance == remaining {
+                        cursor = cursor.next();
+                    } else if distance < remaining && is_last == true && self.split_forward == true
+                    {
+                        cursor.consumed += distance;
+                    } else {
+                        return None;
+                    }
+                }
+                _ => return None,
             }
         }
+        Some(cursor)
     }
 
-    false // If we made it this far, the match was not successful
+    fn candidate_routines(&self, path: &[Move], cursor: Cursor) -> Vec<(Vec<Move>, Cursor)> {
+        let mut candidates: Vec<(Vec<Move>, Cursor)> = Vec::new();
+        let mut routine: Vec<Move> = Vec::new();
+        let mut cursor = cursor;
+        while cursor.idx < path.len() {
+            match path[cursor.idx] {
+                Move::Turn(turn) => routine.push(Move::Turn(turn)),
+                Move::Forward(path_distance) => {
+                    let remaining = path_distance - cursor.consumed;
+                    if self.split_forward == true {
+                        // Stop partway along this move and leave the rest for the next routine
+                        for distance in 1..remaining {
+                            let mut partial = routine.clone();
+                            partial.push(Move::Forward(distance));
+                            if moves_to_string(&partial).len() <= self.sub_routine_limit {
+                                let partial_cursor = Cursor {
+                                    idx: cursor.idx,
+                                    consumed: cursor.consumed + distance,
+                                };
+                                candidates.push((partial, partial_cursor));
+                            }
+                        }
+                    }
+                    routine.push(Move::Forward(remaining));
+                }
+            }
+            cursor = cursor.next();
+
+            if moves_to_string(&routine).len() > self.sub_routine_limit {
+                break;
+            }
+            candidates.push((routine.clone(), cursor));
+        }
+        candidates
+    }
 }
 
-fn find_3_sub_routines(path: &[Segment]) -> (Vec<usize>, Vec<String>) {
-    let path_str = path_to_string(path, false);
-    let candidates = split_sub_routines(path);
+type Edge = ((i32, i32), (i32, i32));
 
-    let mut main_routine: Vec<usize> = Vec::new();
-    let mut sub_routines: Vec<String> = Vec::new();
-    let result = match_path(&path_str, &candidates, &mut main_routine, &mut sub_routines);
-    if result != true {
-        println!("Error! Could not find valid path with only 3 sub routines.");
+fn edge_between(a: (i32, i32), b: (i32, i32)) -> Edge {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
     }
+}
 
-    (main_routine, sub_routines)
+// Walks every traversal of the scaffold that uses each edge exactly once. At each step the robot may go straight or
+// turn, so intersections can be crossed or turned at.
+struct PathPlanner<'a> {
+    camera: &'a Camera,
+    edge_count: usize,
+    used: HashSet<Edge>,
+    moves: Vec<Move>,
+}
+
+impl<'a> PathPlanner<'a> {
+    fn new(camera: &'a Camera) -> Self {
+        let mut edges: HashSet<Edge> = HashSet::new();
+        for &coord in camera.area.keys() {
+            if camera.is_scaffold(coord) == false {
+                continue;
+            }
+            for &dir in [Cardinal::East, Cardinal::South].iter() {
+                let neighbor = dir.step_from(coord);
+                if camera.is_scaffold(neighbor) == true {
+                    edges.insert(edge_between(coord, neighbor));
+                }
+            }
+        }
+
+        Self {
+            camera,
+            edge_count: edges.len(),
+            used: HashSet::new(),
+            moves: Vec::new(),
+        }
+    }
+
+    fn plan<T, F>(&mut self, visit: &mut F) -> Option<T>
+    where
+        F: FnMut(&[Move]) -> Option<T>,
+    {
+        let (coord, dir) = self.camera.find_robot();
+        self.step(coord, dir, true, visit)
+    }
+
+    fn step<T, F>(
+        &mut self,
+        coord: (i32, i32),
+        dir: Cardinal,
+        first: bool,
+        visit: &mut F,
+    ) -> Option<T>
+    where
+        F: FnMut(&[Move]) -> Option<T>,
+    {
+        // Going straight is tried first so the simplest path is always the first candidate
+        let mut choices = vec![
+            (vec![], dir),
+            (vec![Turn::Left], dir.turn(Turn::Left)),
+            (vec![Turn::Right], dir.turn(Turn::Right)),
+        ];
+        if first == true {
+            // The robot might start out facing away from the scaffold
+            choices.push((vec![Turn::Left, Turn::Left], dir.opposite()));
+        }
+
+        let mut stuck = true;
+        for (turns, next_dir) in choices {
+            let next_coord = next_dir.step_from(coord);
+            let edge = edge_between(coord, next_coord);
+            if self.camera.is_scaffold(next_coord) == false || self.used.contains(&edge) == true {
+                continue;
+            }
+            stuck = false;
+
+            let moves_len = self.moves.len();
+            self.used.insert(edge);
+            self.moves.extend(turns.into_iter().map(Move::Turn));
+            self.moves.push(Move::Forward(1));
+
+            let result = self.step(next_coord, next_dir, false, visit);
+
+            self.moves.truncate(moves_len);
+            self.used.remove(&edge);
+            if result.is_some() {
+                return result;
+            }
+        }
+
+        if stuck == true && self.used.len() == self.edge_count {
+            visit(&merge_forwards(&self.moves))
+        } else {
+            None
+        }
+    }
 }
 
 #[aoc(day17, part2)]
@@ -764,15 +1045,21 @@ pub fn solve(input: &str) -> i64 {
     let mut camera = Camera::new(program);
     camera.snap();
     //camera.display();
-    let path = camera.find_path();
-    let (main_routine, sub_routines) = find_3_sub_routines(&path);
+    let encoding = [false, true]
+        .iter()
+        .find_map(|&split_forward| {
+            let compressor =
+                Compressor::new(ROUTINE_COUNT, ROUTINE_LIMIT, ROUTINE_LIMIT, split_forward);
+            camera.plan_paths(|path| compressor.best_encoding(path))
+        })
+        .expect("Could not compress any path into sub routines");
 
     let mut control_program = Program::new(&code, &[]);
     control_program.code[0] = 2; // Wake up robot
 
     let mut camera = Camera::new(control_program);
-    camera.give_main_routine(&main_routine);
-    camera.give_sub_routines(&sub_routines);
+    camera.give_main_routine(&encoding);
+    camera.give_sub_routines(&encoding, ROUTINE_COUNT);
     camera.enable_video(false);
 
     let dust = camera.feed();
@@ -785,7 +1072,7 @@ mod test {
     use super::*;
 
     #[test]
-    fn test_find_3_sub_routines() {
+    fn test_compressor() {
         let path = [
             (Turn::Right, 8),
             (Turn::Right, 8),
@@ -806,10 +1093,159 @@ mod test {
             .iter()
             .map(|&(turn, distance)| Segment { turn, distance })
             .collect();
-        let (main_routine, sub_routines) = find_3_sub_routines(&path_segments);
-        assert_eq!(main_routine, [0, 1, 2, 1, 0, 2]);
-        assert_eq!(sub_routines[0], "R8R8");
-        assert!((sub_routines[1] == "R4R4R8") || (sub_routines[1] == "R4R4")); // Note: there are multiple valid answers, the example is just one!
-        assert!((sub_routines[2] == "L6L2") || (sub_routines[2] == "R8L6L2"));
+        let moves = path_to_moves(&path_segments);
+        let compressor = Compressor::new(ROUTINE_COUNT, ROUTINE_LIMIT, ROUTINE_LIMIT, false);
+
+        let encodings = compressor.all_encodings(&moves);
+        let example = Encoding {
+            main_routine: vec![0, 1, 2, 1, 0, 2],
+            sub_routines: vec![
+                vec![
+                    Move::Turn(Turn::Right),
+                    Move::Forward(8),
+                    Move::Turn(Turn::Right),
+                    Move::Forward(8),
+                ],
+                vec![
+                    Move::Turn(Turn::Right),
+                    Move::Forward(4),
+                    Move::Turn(Turn::Right),
+                    Move::Forward(4),
+                    Move::Turn(Turn::Right),
+                    Move::Forward(8),
+                ],
+                vec![
+                    Move::Turn(Turn::Left),
+                    Move::Forward(6),
+                    Move::Turn(Turn::Left),
+                    Move::Forward(2),
+                ],
+            ],
+        };
+        assert!(encodings.contains(&example)); // Note: there are multiple valid answers, the example is just one!
+        assert_eq!(example.main_routine_string(), "A,B,C,B,A,C");
+        assert_eq!(example.sub_routine_strings()[1], "R,4,R,4,R,8");
+
+        for encoding in &encodings {
+            assert_eq!(encoding.expand(), moves);
+            assert!(encoding.sub_routines.len() <= ROUTINE_COUNT);
+            assert!(encoding.main_routine_string().len() <= ROUTINE_LIMIT);
+            assert!(encoding
+                .sub_routine_strings()
+                .iter()
+                .all(|s| s.len() <= ROUTINE_LIMIT));
+        }
+
+        let best = compressor.best_encoding(&moves).unwrap();
+        assert!(encodings
+            .iter()
+            .all(|e| best.encoded_len() <= e.encoded_len()));
+    }
+
+    #[test]
+    fn test_compressor_split_forward() {
+        let moves = [
+            Move::Turn(Turn::Right),
+            Move::Forward(12),
+            Move::Turn(Turn::Right),
+            Move::Forward(12),
+        ];
+
+        // "12" doesn't fit in a one character routine
+        let compressor = Compressor::new(2, 1, 20, false);
+        assert_eq!(compressor.best_encoding(&moves), None);
+
+        let compressor = Compressor::new(2, 1, 20, true);
+        let best = compressor.best_encoding(&moves).unwrap();
+        assert_eq!(best.main_routine_string(), "A,B,B,A,B,B");
+        assert_eq!(best.sub_routine_strings(), ["R", "6"]);
+        assert_eq!(best.expand(), moves);
+    }
+
+    fn camera_from_string(string: &str) -> Camera {
+        let mut camera = Camera::new(Program::new(&[99], &[]));
+        for (y, line) in string.lines().enumerate() {
+            for (x, c) in line.trim().chars().enumerate() {
+                camera
+                    .area
+                    .insert((x as i32, y as i32), Space::from_value(c as i64));
+            }
+        }
+        camera
+    }
+
+    // Follow the moves from the robot's start, returning every scaffold cell visited
+    fn walk(camera: &Camera, moves: &[Move]) -> HashSet<(i32, i32)> {
+        let (mut coord, mut dir) = camera.find_robot();
+        let mut visited: HashSet<(i32, i32)> = HashSet::new();
+        visited.insert(coord);
+        for &m in moves {
+            match m {
+                Move::Turn(turn) => dir = dir.turn(turn),
+                Move::Forward(distance) => {
+                    for _ in 0..distance {
+                        coord = dir.step_from(coord);
+                        assert!(camera.is_scaffold(coord));
+                        visited.insert(coord);
+                    }
+                }
+            }
+        }
+        visited
+    }
+
+    #[test]
+    fn test_plan_paths() {
+        let camera = camera_from_string(
+            "#######...#####
+            #.....#...#...#
+            #.....#...#...#
+            ......#...#...#
+            ......#...###.#
+            ......#.....#.#
+            ^########...#.#
+            ......#.#...#.#
+            ......#########
+            ........#...#..
+            ....#########..
+            ....#...#......
+            ....#...#......
+            ....#...#......
+            ....#####......",
+        );
+        let scaffold: HashSet<(i32, i32)> = camera
+            .area
+            .keys()
+            .copied()
+            .filter(|&coord| camera.is_scaffold(coord))
+            .collect();
+
+        // The first candidate goes straight through every intersection
+        let first = camera.plan_paths(|path| Some(path.to_vec())).unwrap();
+        assert_eq!(
+            moves_to_string(&first),
+            "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2"
+        );
+
+        let mut candidates: Vec<Vec<Move>> = Vec::new();
+        let result: Option<()> = camera.plan_paths(|path| {
+            candidates.push(path.to_vec());
+            None
+        });
+        assert_eq!(result, None);
+        assert!(candidates.len() > 1);
+        for candidate in &candidates {
+            assert_eq!(walk(&camera, candidate), scaffold);
+        }
+
+        // Paths that turn at an intersection are offered too
+        let turning = candidates.iter().find(|path| {
+            path.starts_with(&[
+                Move::Turn(Turn::Right),
+                Move::Forward(6),
+                Move::Turn(Turn::Right),
+            ])
+        });
+        assert!(turning.is_some());
     }
 }
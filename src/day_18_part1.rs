@@ -92,8 +92,11 @@
     How many steps is the shortest path that collects all of the keys?
 */
 
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -242,289 +245,220 @@ impl Map {
         println!();
     }
 
-    fn get_entrance(&self) -> Point {
+    fn get_entrances(&self) -> Vec<Point> {
+        let mut entrances = Vec::new();
         for (&k, &v) in self.area.iter() {
-            if let Space::Entrance = v {
-                return k;
+            if v == Space::Entrance {
+                entrances.push(k);
             }
         }
 
-        panic!("Could not find entrance");
-    }
-
-    fn find_keys(&self, start_node: &Node) -> Vec<(Node, u32)> {
-        let mut frontier: Vec<Point> = Vec::new();
-        let mut walked: HashSet<Point> = HashSet::new();
-
-        frontier.push(start_node.point);
-        walked.insert(start_node.point);
-
-        let mut nodes: Vec<(Node, u32)> = Vec::new();
-        let mut counter = 0;
-        loop {
-            counter += 1;
-            for location in frontier.drain(..).collect::<Vec<Point>>() {
-                let candidates = [
-                    Cardinal::North,
-                    Cardinal::South,
-                    Cardinal::West,
-                    Cardinal::East,
-                ];
-                for direction in candidates.iter() {
-                    let step_in_direction = direction.step_from(location);
-                    //println!("Step: {:?}", step_in_direction);
-                    if walked.get(&step_in_direction) == None {
-                        match self.area.get(&step_in_direction) {
-                            Some(Space::Empty) | Some(Space::Entrance) => {
-                                frontier.push(step_in_direction);
-                                walked.insert(step_in_direction);
-                            }
-                            Some(Space::Key(c)) => {
-                                if start_node.has_key(*c) == false {
-                                    //println!("Key: {}", c);
-                                    let mut node = Node {
-                                        point: step_in_direction,
-                                        space: Space::Key(*c),
-                                        inventory: start_node.inventory,
-                                    };
-                                    node.set_key(*c, true);
-                                    nodes.push((node, counter));
-                                } else {
-                                    frontier.push(step_in_direction);
-                                    walked.insert(step_in_direction);
-                                }
-                            }
-                            Some(Space::Door(c)) => {
-                                if start_node.has_key(*c) == true {
-                                    //println!("Door: {}", c);
-                                    frontier.push(step_in_direction);
-                                    walked.insert(step_in_direction);
-                                }
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-            }
-
-            //println!("frontier: {:?}", frontier);
-            if frontier.is_empty() == true {
-                break;
+        // Put the list of entrances in order from top to bottom, left to right.
+        // This makes the path results predictable and testable but is not strictly required.
+        entrances.sort_by(|a, b| {
+            let primary = a.y.cmp(&b.y);
+            match primary {
+                Ordering::Equal => a.x.cmp(&b.x),
+                _ => primary,
             }
-        }
-
-        nodes
+        });
+        entrances
     }
 
-    fn build_graph(&self) -> Graph {
-        let entrance = self.get_entrance();
-        //println!("Entrance: {:?}", entrance);
+    fn get_keys(&self) -> Vec<(char, Point)> {
+        let mut keys: Vec<(char, Point)> = self
+            .area
+            .iter()
+            .filter_map(|(&p, &space)| match space {
+                Space::Key(c) => Some((c, p)),
+                _ => None,
+            })
+            .collect();
+        keys.sort_by_key(|&(c, _)| c);
+        keys
+    }
 
-        let starting_vertex = Vertex {
-            id: 0,
-            node: Node {
-                point: entrance,
-                space: Space::Entrance,
-                inventory: 0,
+    // Shortest walks from `start` to every key, ignoring doors but noting which ones are on the way
+    fn paths_from(&self, start: Point) -> HashMap<char, KeyPath> {
+        let mut paths = HashMap::new();
+        let mut walked: HashSet<Point> = HashSet::new();
+        let mut frontier: VecDeque<(Point, KeyPath)> = VecDeque::new();
+
+        walked.insert(start);
+        frontier.push_back((
+            start,
+            KeyPath {
+                distance: 0,
+                doors: 0,
             },
-            distance: 0,
-            pi: None,
-            connected: Vec::new(),
-        };
-
-        let mut node_to_vertex_map: HashMap<Node, usize> = HashMap::new();
-        node_to_vertex_map.insert(starting_vertex.node, starting_vertex.id);
+        ));
+        while let Some((location, path)) = frontier.pop_front() {
+            let candidates = [
+                Cardinal::North,
+                Cardinal::South,
+                Cardinal::West,
+                Cardinal::East,
+            ];
+            for direction in candidates.iter() {
+                let step_in_direction = direction.step_from(location);
+                if walked.contains(&step_in_direction) == true {
+                    continue;
+                }
 
-        let mut graph = Graph {
-            vertices: Vec::new(),
-        };
-        graph.vertices.push(starting_vertex);
-
-        let mut frontier: Vec<usize> = vec![0]; // Visit the starting vertex first
-
-        loop {
-            for frontier_id in frontier.drain(..).collect::<Vec<usize>>() {
-                let connected_nodes = self.find_keys(&graph.vertices[frontier_id].node);
-                /*println!("Nodes:");
-                for (node, distance) in &connected_nodes {
-                    println!("   {:?} @ {}", node, distance);
-                }*/
-
-                // Check if any of these connected nodes are new. If they are, create them as vertices in the graph.
-                for (node, distance) in connected_nodes {
-                    let id;
-                    if let Some(&idx) = node_to_vertex_map.get(&node) {
-                        // This one exists. Save its ID.
-                        id = idx;
-                    } else {
-                        // This one doesn't exist. Create it and save its ID.
-                        id = graph.vertices.len();
-                        node_to_vertex_map.insert(node, id);
-
-                        let v = Vertex {
-                            id,
-                            node,
-                            distance: u32::max_value(),
-                            pi: Some(graph.vertices[frontier_id].id),
-                            connected: Vec::new(),
-                        };
-                        graph.vertices.push(v);
-                        frontier.push(id);
+                let mut next = KeyPath {
+                    distance: path.distance + 1,
+                    doors: path.doors,
+                };
+                match self.area.get(&step_in_direction) {
+                    Some(Space::Empty) | Some(Space::Entrance) => (),
+                    Some(Space::Key(c)) => {
+                        paths.insert(*c, next);
                     }
-
-                    // Create an edge based on the saved ID and add it to the current node in the graph.
-                    let edge = Edge {
-                        vertex_id: id,
-                        distance,
-                    };
-                    graph.vertices[frontier_id].connected.push(edge);
+                    Some(Space::Door(c)) => next.doors |= key_bit(*c),
+                    _ => continue,
                 }
-            }
-
-            //println!("Graph frontier: {:?}", frontier);
-            if frontier.is_empty() == true {
-                break;
+                walked.insert(step_in_direction);
+                frontier.push_back((step_in_direction, next));
             }
         }
 
-        //println!("Graph: {:#?}", graph);
-        graph
+        paths
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Node {
-    point: Point,
-    space: Space,
-    inventory: u32,
+// Keys are numbered by letter, so a set of keys (or of the doors they open) fits in a u32
+fn key_bit(c: char) -> u32 {
+    1 << (c as u32 - 'a' as u32)
 }
 
-impl Node {
-    fn idx_from_char(c: char) -> usize {
-        if c.is_ascii_lowercase() == false {
-            panic!("Invalid character!");
-        }
-
-        let idx = (c as u8) - b'a';
-        idx as usize
-    }
-
-    fn has_key(&self, c: char) -> bool {
-        let idx = Node::idx_from_char(c);
-        let key_bit = self.inventory & (1 << idx);
-        key_bit != 0
-    }
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct KeyPath {
+    distance: u32,
+    doors: u32,
+}
 
-    fn set_key(&mut self, c: char, value: bool) {
-        let idx = Node::idx_from_char(c);
-        let key_bit = if value == true { 1 } else { 0 };
-        self.inventory |= key_bit << idx;
-    }
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct State {
+    // Where each robot is: an entrance while it hasn't moved, then the last key it collected
+    positions: Vec<usize>,
+    keys: u32,
 }
 
-#[derive(Debug)]
-struct Edge {
-    vertex_id: usize,
+#[derive(Debug, PartialEq)]
+struct Route {
     distance: u32,
+    // Each key in the order it was collected, with the robot that collected it
+    keys: Vec<(usize, char)>,
 }
 
-#[derive(Debug)]
-struct Vertex {
-    id: usize, // Not technically needed but useful for debug
-    node: Node,
-    distance: u32, // This is used purely for Dijkstra's, where u32::max_value() is used to indicate infinity distance
-    pi: Option<usize>, // The "parent" or the vertex from which the fastest path comes
-    connected: Vec<Edge>,
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|(robot, key)| format!("{}:{}", robot, key))
+            .collect();
+        write!(f, "{} steps: {}", self.distance, keys.join(", "))
+    }
 }
 
-struct Graph {
-    vertices: Vec<Vertex>,
+// The distances between every entrance and key, searched over (robot positions, keys held)
+struct Vault {
+    keys: Vec<char>,
+    robots: usize,
+    // paths[from][to], where `from` counts the entrances first and then the keys, and `to` is a key
+    paths: Vec<Vec<Option<KeyPath>>>,
 }
 
-impl Graph {
-    fn dijkstra(&mut self, start_vertex: usize) {
-        // Set initial conditions:
-        //  - set all distances to infinity except the starting vertex which is set to 0
-        //  - set the starting vertex as the first vertex to process
-        for vertex in self.vertices.iter_mut() {
-            vertex.distance = u32::max_value();
+impl Vault {
+    fn from_map(map: &Map) -> Vault {
+        let entrances = map.get_entrances();
+        let keys = map.get_keys();
+        let paths = entrances
+            .iter()
+            .chain(keys.iter().map(|(_, p)| p))
+            .map(|&start| {
+                let found = map.paths_from(start);
+                keys.iter().map(|(c, _)| found.get(c).copied()).collect()
+            })
+            .collect();
+
+        Vault {
+            keys: keys.iter().map(|&(c, _)| c).collect(),
+            robots: entrances.len(),
+            paths,
         }
-        self.vertices[start_vertex].distance = 0;
-
-        let mut visited: HashSet<usize> = HashSet::new();
-        let mut next: HashSet<usize> = HashSet::new();
-        next.insert(start_vertex);
-
-        while next.is_empty() == false {
-            // Find the next vertex with minimum distance. Mark it as visited
-            // (we're visiting it now) and remove it from the 'next' list.
-            let mut min_id = 0;
-            let mut min_value = u32::max_value();
-            for &vertex_id in next.iter() {
-                if self.vertices[vertex_id].distance < min_value {
-                    min_value = self.vertices[vertex_id].distance;
-                    min_id = vertex_id;
-                }
-            }
-            let current_vertex_id = min_id;
-            visited.insert(current_vertex_id);
-            next.remove(&min_id);
-            //println!("Dijkstra visit vertex #{}", current_vertex_id);
-
-            // Update distances of all adjacent vertices and add them to the 'next' list.
-            // Skip any vertices we've already visited.
-            let mut temp: Vec<(usize, u32, Option<usize>)> = Vec::new();
-            for e in self.vertices[current_vertex_id].connected.iter() {
-                if visited.get(&e.vertex_id) != None {
-                    continue;
-                }
+    }
 
-                let new_distance = self.vertices[current_vertex_id].distance + e.distance;
-                let old_distance = self.vertices[e.vertex_id].distance;
-                if new_distance < old_distance {
-                    temp.push((e.vertex_id, new_distance, Some(current_vertex_id)));
-                }
+    fn all_keys(&self) -> u32 {
+        self.keys.iter().fold(0, |mask, &c| mask | key_bit(c))
+    }
 
-                next.insert(e.vertex_id);
+    // Dijkstra over states. Returns None if some key can never be collected.
+    fn solve(&self) -> Option<Route> {
+        let start = State {
+            positions: (0..self.robots).collect(),
+            keys: 0,
+        };
+        let mut distances: HashMap<State, u32> = HashMap::new();
+        let mut parents: HashMap<State, (State, usize, char)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        distances.insert(start.clone(), 0);
+        heap.push(Reverse((0, start.keys, start.positions.clone())));
+
+        while let Some(Reverse((distance, keys, positions))) = heap.pop() {
+            let state = State { positions, keys };
+            if distances.get(&state) != Some(&distance) {
+                continue;
             }
-
-            for (vertex_id, distance, pi) in temp {
-                self.vertices[vertex_id].distance = distance;
-                self.vertices[vertex_id].pi = pi;
+            if state.keys == self.all_keys() {
+                return Some(self.route(distance, &state, &parents));
             }
-        }
-    }
 
-    fn get_shortest_path(&self) -> (u32, Vec<Space>) {
-        let mut longest_inventory = 0;
-        let mut shortest_distance = u32::max_value();
-        let mut shortest_end_vertex_id = 0;
-        for vertex in self.vertices.iter() {
-            let current_inventory = vertex.node.inventory;
-            if current_inventory > longest_inventory {
-                longest_inventory = current_inventory;
-                shortest_distance = u32::max_value();
-                shortest_end_vertex_id = 0;
-            }
+            for (robot, &from) in state.positions.iter().enumerate() {
+                for (key_idx, path) in self.paths[from].iter().enumerate() {
+                    let bit = key_bit(self.keys[key_idx]);
+                    let path = match path {
+                        Some(path) if state.keys & bit == 0 && path.doors & !state.keys == 0 => {
+                            path
+                        }
+                        _ => continue,
+                    };
 
-            if vertex.distance < shortest_distance {
-                shortest_distance = vertex.distance;
-                shortest_end_vertex_id = vertex.id;
+                    let mut next = state.clone();
+                    next.positions[robot] = self.robots + key_idx;
+                    next.keys |= bit;
+                    let next_distance = distance + path.distance;
+                    let improved = match distances.get(&next) {
+                        Some(&d) => next_distance < d,
+                        None => true,
+                    };
+                    if improved == true {
+                        distances.insert(next.clone(), next_distance);
+                        parents.insert(next.clone(), (state.clone(), robot, self.keys[key_idx]));
+                        heap.push(Reverse((next_distance, next.keys, next.positions)));
+                    }
+                }
             }
         }
 
-        //println!("End vertex: {:?}", graph[shortest_end_vertex_id]);
+        None
+    }
 
-        let mut path: Vec<Space> = Vec::new();
-        let mut current_vertex_id = shortest_end_vertex_id;
-        path.push(self.vertices[current_vertex_id].node.space);
-        while let Some(parent_id) = self.vertices[current_vertex_id].pi {
-            current_vertex_id = parent_id;
-            path.push(self.vertices[current_vertex_id].node.space);
+    fn route(
+        &self,
+        distance: u32,
+        end: &State,
+        parents: &HashMap<State, (State, usize, char)>,
+    ) -> Route {
+        let mut keys = Vec::new();
+        let mut state = end;
+        while let Some((parent, robot, key)) = parents.get(state) {
+            keys.push((*robot, *key));
+            state = parent;
         }
-        path.reverse();
-
-        (self.vertices[shortest_end_vertex_id].distance, path)
+        keys.reverse();
+        Route { distance, keys }
     }
 }
 
@@ -533,50 +467,35 @@ pub fn solve(input: &str) -> u32 {
     let map = Map::from_string(&input);
     map.display();
 
-    let mut graph = map.build_graph();
-
-    /*println!("Graph:");
-    println!("   {} vertices", graph.vertices.len());
-    for (id, vertex) in graph.vertices.iter().enumerate() {
-        println!("   Vertex {}:", id);
-        println!("      id: {}", vertex.id);
-        println!("      node.point: {:?}", vertex.node.point);
-        println!("      node.space: {:?}", vertex.node.space);
-        println!("      node.inventory: {}", vertex.node.inventory);
-        println!("      distance: {}", vertex.distance);
-        println!("      pi: {:?}", vertex.pi);
-        println!("      edges:");
-        for e in vertex.connected.iter() {
-            println!("         {:?}", e);
-        }
-        println!();
-    }*/
-
-    graph.dijkstra(0);
-
-    let (distance, path) = graph.get_shortest_path();
-    println!("Distance: {}", distance);
-    println!("Path: {:?}", path);
-    distance
+    let route = Vault::from_map(&map)
+        .solve()
+        .expect("Some keys can't be reached");
+    println!("Route: {}", route);
+    route.distance
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn solve_map(input: &str) -> Route {
+        Vault::from_map(&Map::from_string(input)).solve().unwrap()
+    }
+
+    fn route_keys(route: &Route) -> String {
+        route.keys.iter().map(|&(_, key)| key).collect()
+    }
+
     #[test]
-    fn test_get_shortest_path() {
+    fn test_vault_solve() {
         let input = "
 #########
 #b.A.@.a#
 #########
 ";
-        let map = Map::from_string(&input);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, path) = graph.get_shortest_path();
-        assert_eq!(distance, 8);
-        assert_eq!(path, [Space::Entrance, Space::Key('a'), Space::Key('b')]);
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 8);
+        assert_eq!(route_keys(&route), "ab");
 
         let input = "
 ########################
@@ -585,23 +504,9 @@ mod test {
 #d.....................#
 ########################
 ";
-        let map = Map::from_string(&input);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, path) = graph.get_shortest_path();
-        assert_eq!(distance, 86);
-        assert_eq!(
-            path,
-            [
-                Space::Entrance,
-                Space::Key('a'),
-                Space::Key('b'),
-                Space::Key('c'),
-                Space::Key('d'),
-                Space::Key('e'),
-                Space::Key('f')
-            ]
-        );
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 86);
+        assert_eq!(route_keys(&route), "abcdef");
 
         let input = "
 ########################
@@ -610,24 +515,9 @@ mod test {
 #.....@.a.B.c.d.A.e.F.g#
 ########################
 ";
-        let map = Map::from_string(&input);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, path) = graph.get_shortest_path();
-        assert_eq!(distance, 132);
-        assert_eq!(
-            path,
-            [
-                Space::Entrance,
-                Space::Key('b'),
-                Space::Key('a'),
-                Space::Key('c'),
-                Space::Key('d'),
-                Space::Key('f'),
-                Space::Key('e'),
-                Space::Key('g')
-            ]
-        );
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 132);
+        assert_eq!(route_keys(&route), "bacdfeg");
 
         let input = "
 #################
@@ -640,11 +530,8 @@ mod test {
 #l.F..d...h..C.m#
 #################
 ";
-        let map = Map::from_string(&input);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, _path) = graph.get_shortest_path();
-        assert_eq!(distance, 136);
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 136);
         // There are multiple possible paths, don't verify
 
         let input = "
@@ -655,11 +542,12 @@ mod test {
 ###g#h#i################
 ########################
 ";
-        let map = Map::from_string(&input);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, _path) = graph.get_shortest_path();
-        assert_eq!(distance, 81);
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 81);
         // There are multiple possible paths, don't verify
+
+        // Nothing opens door A
+        let map = Map::from_string("######\n#@.Ab#\n######");
+        assert_eq!(Vault::from_map(&map).solve(), None);
     }
 }
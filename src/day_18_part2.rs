@@ -130,9 +130,11 @@
     After updating your map and using the remote-controlled robots, what is the fewest steps necessary to collect all of the keys?
 */
 
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -188,7 +190,7 @@ struct Point {
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum Space {
-    Unknown,
+    Unknown(char),
     Empty,
     Wall,
     Entrance,
@@ -204,13 +206,13 @@ impl Space {
             '@' => Self::Entrance,
             'a'..='z' => Self::Key(value),
             'A'..='Z' => Self::Door(value.to_lowercase().collect::<Vec<_>>()[0]),
-            _ => Self::Unknown,
+            _ => Self::Unknown(value),
         }
     }
 
     fn char(&self) -> char {
         match *self {
-            Self::Unknown => '?',
+            Self::Unknown(c) => c,
             Self::Empty => ' ',
             Self::Wall => '#',
             Self::Entrance => '@',
@@ -232,13 +234,9 @@ impl Map {
         let mut p = Point { x: 0, y: 0 };
         for line in input.lines() {
             for c in line.chars() {
-                let space = Space::from_value(c);
-                if space != Space::Unknown {
-                    area.insert(p, space);
-                    p.x += 1;
-                } else {
-                    panic!("Unknown input: 0x{:02x}", c as u8);
-                }
+                // Unknown spaces are kept so that `validate` can point them out
+                area.insert(p, Space::from_value(c));
+                p.x += 1;
             }
             p.x = 0;
             p.y += 1;
@@ -333,303 +331,407 @@ impl Map {
         }
     }
 
-    fn find_keys(&self, start_node: &Node) -> Vec<(Node, u32)> {
-        let mut frontier: Vec<Point> = Vec::new();
-        let mut walked: HashSet<Point> = HashSet::new();
+    fn get_keys(&self) -> Vec<(char, Point)> {
+        let mut keys: Vec<(char, Point)> = self
+            .area
+            .iter()
+            .filter_map(|(&p, &space)| match space {
+                Space::Key(c) => Some((c, p)),
+                _ => None,
+            })
+            .collect();
+        keys.sort_by_key(|&(c, _)| c);
+        keys
+    }
 
-        frontier.push(start_node.point);
-        walked.insert(start_node.point);
+    // Shortest walks from `start` to every key, ignoring doors but noting which ones are on the way
+    fn paths_from(&self, start: Point) -> HashMap<char, KeyPath> {
+        let mut paths = HashMap::new();
+        let mut walked: HashSet<Point> = HashSet::new();
+        let mut frontier: VecDeque<(Point, KeyPath)> = VecDeque::new();
+
+        walked.insert(start);
+        frontier.push_back((
+            start,
+            KeyPath {
+                distance: 0,
+                doors: 0,
+            },
+        ));
+        while let Some((location, path)) = frontier.pop_front() {
+            let candidates = [
+                Cardinal::North,
+                Cardinal::South,
+                Cardinal::West,
+                Cardinal::East,
+            ];
+            for direction in candidates.iter() {
+                let step_in_direction = direction.step_from(location);
+                if walked.contains(&step_in_direction) == true {
+                    continue;
+                }
 
-        let mut nodes: Vec<(Node, u32)> = Vec::new();
-        let mut counter = 0;
-        loop {
-            counter += 1;
-            for location in frontier.drain(..).collect::<Vec<Point>>() {
-                let candidates = [
-                    Cardinal::North,
-                    Cardinal::South,
-                    Cardinal::West,
-                    Cardinal::East,
-                ];
-                for direction in candidates.iter() {
-                    let step_in_direction = direction.step_from(location);
-                    //println!("Step: {:?}", step_in_direction);
-                    if walked.get(&step_in_direction) == None {
-                        match self.area.get(&step_in_direction) {
-                            Some(Space::Empty) | Some(Space::Entrance) => {
-                                frontier.push(step_in_direction);
-                                walked.insert(step_in_direction);
-                            }
-                            Some(Space::Key(c)) => {
-                                if start_node.has_key(*c) == false {
-                                    //println!("Key: {}", c);
-                                    let mut node = Node {
-                                        point: step_in_direction,
-                                        space: Space::Key(*c),
-                                        inventory: start_node.inventory,
-                                    };
-                                    node.set_key(*c, true);
-                                    nodes.push((node, counter));
-                                } else {
-                                    frontier.push(step_in_direction);
-                                    walked.insert(step_in_direction);
-                                }
-                            }
-                            Some(Space::Door(c)) => {
-                                if start_node.has_key(*c) == true {
-                                    //println!("Door: {}", c);
-                                    frontier.push(step_in_direction);
-                                    walked.insert(step_in_direction);
-                                }
-                            }
-                            _ => (),
-                        }
+                let mut next = KeyPath {
+                    distance: path.distance + 1,
+                    doors: path.doors,
+                };
+                match self.area.get(&step_in_direction) {
+                    Some(Space::Empty) | Some(Space::Entrance) => (),
+                    Some(Space::Key(c)) => {
+                        paths.insert(*c, next);
                     }
+                    Some(Space::Door(c)) => next.doors |= key_bit(*c),
+                    _ => continue,
                 }
-            }
-
-            //println!("frontier: {:?}", frontier);
-            if frontier.is_empty() == true {
-                break;
+                walked.insert(step_in_direction);
+                frontier.push_back((step_in_direction, next));
             }
         }
 
-        nodes
+        paths
     }
 
-    fn build_graph(&self) -> Graph {
-        // Attach all other entrances to the starting vertex
-        let entrances = self.get_entrances();
-        let mut starting_vertex = Vertex {
-            id: 0,
-            nodes: Vec::new(),
-            distance: 0,
-            pi: None,
-            connected: Vec::new(),
-        };
-        for e in entrances {
-            let n = Node {
-                point: e,
-                space: Space::Entrance,
-                inventory: 0,
-            };
-            starting_vertex.nodes.push(n);
+    // Everywhere reachable from the given points, through the doors whose keys are in `keys`
+    fn flood(&self, starts: &[Point], keys: u32) -> HashSet<Point> {
+        let mut walked: HashSet<Point> = starts.iter().copied().collect();
+        let mut frontier: Vec<Point> = starts.to_vec();
+        while let Some(location) = frontier.pop() {
+            let candidates = [
+                Cardinal::North,
+                Cardinal::South,
+                Cardinal::West,
+                Cardinal::East,
+            ];
+            for direction in candidates.iter() {
+                let step_in_direction = direction.step_from(location);
+                if walked.contains(&step_in_direction) == true {
+                    continue;
+                }
+                let passable = match self.area.get(&step_in_direction) {
+                    Some(Space::Empty) | Some(Space::Entrance) | Some(Space::Key(_)) => true,
+                    Some(Space::Door(c)) => keys & key_bit(*c) != 0,
+                    _ => false,
+                };
+                if passable == true {
+                    walked.insert(step_in_direction);
+                    frontier.push(step_in_direction);
+                }
+            }
         }
+        walked
+    }
 
-        let mut node_to_vertex_map: HashMap<Vec<Node>, usize> = HashMap::new();
-        node_to_vertex_map.insert(starting_vertex.nodes.clone(), starting_vertex.id);
+    // Everything that would stop the vault from being solved. Robots that are allowed to be
+    // walled off from each other share keys but each must reach its own.
+    fn validate(&self, separate_robots: bool) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut points: Vec<(&Point, &Space)> = self.area.iter().collect();
+        points.sort_by_key(|(p, _)| (p.y, p.x));
+
+        let mut keys: HashMap<char, Point> = HashMap::new();
+        for &(&p, &space) in points.iter() {
+            match space {
+                Space::Unknown(c) => problems.push(Problem::UnknownSpace(p, c)),
+                Space::Key(c) => {
+                    if keys.contains_key(&c) == true {
+                        problems.push(Problem::DuplicateKey(p, c));
+                    } else {
+                        keys.insert(c, p);
+                    }
+                }
+                _ => (),
+            }
+        }
+        for &(&p, &space) in points.iter() {
+            if let Space::Door(c) = space {
+                if keys.contains_key(&c) == false {
+                    problems.push(Problem::DoorWithoutKey(p, c));
+                }
+            }
+        }
 
-        let mut graph = Graph {
-            vertices: Vec::new(),
-        };
-        graph.vertices.push(starting_vertex);
+        let entrances = self.get_entrances();
+        if entrances.is_empty() == true {
+            problems.push(Problem::NoEntrance);
+            return problems;
+        }
+        if separate_robots == false {
+            let region = self.flood(&entrances[..1], u32::MAX);
+            for &e in entrances.iter().skip(1) {
+                if region.contains(&e) == false {
+                    problems.push(Problem::SeparateEntrance(e));
+                }
+            }
+        }
 
-        let mut frontier: Vec<usize> = vec![0]; // Visit the starting vertex first
+        let all_doors_open = self.flood(&entrances, u32::MAX);
+        let mut sorted_keys: Vec<(char, Point)> = keys.into_iter().collect();
+        sorted_keys.sort_by_key(|&(c, _)| c);
+        let mut flagged: HashSet<char> = HashSet::new();
+        for &(c, p) in sorted_keys.iter() {
+            if all_doors_open.contains(&p) == false {
+                problems.push(Problem::UnreachableKey(p, c));
+                flagged.insert(c);
+            } else if self.flood(&entrances, !key_bit(c)).contains(&p) == false {
+                problems.push(Problem::KeyBehindOwnDoor(p, c));
+                flagged.insert(c);
+            }
+        }
 
+        // Collect whatever can be reached until nothing new opens up. Any key left over waits on
+        // doors that can never be opened.
+        let mut collected = 0;
         loop {
-            for frontier_id in frontier.drain(..).collect::<Vec<usize>>() {
-                for node_idx in 0..graph.vertices[frontier_id].nodes.len() {
-                    let connected_nodes =
-                        self.find_keys(&graph.vertices[frontier_id].nodes[node_idx]);
-                    /*println!("Nodes:");
-                    for (node, distance) in &connected_nodes {
-                        println!("   {:?} @ {}", node, distance);
-                    }*/
-
-                    // Check if any of these connected nodes are new. If they are, create them as vertices in the graph.
-                    for (node, distance) in connected_nodes {
-                        // Create a new nodes array. The current node is replaced, and also every other node
-                        // must have its inventory updated (all inventories are just copies of each other for laziness reasons).
-                        let mut nodes = graph.vertices[frontier_id].nodes.clone();
-                        nodes[node_idx] = node;
-                        for n in &mut nodes {
-                            n.inventory = node.inventory;
-                        }
+            let reachable = self.flood(&entrances, collected);
+            let more = sorted_keys
+                .iter()
+                .filter(|(_, p)| reachable.contains(p))
+                .fold(collected, |mask, &(c, _)| mask | key_bit(c));
+            if more == collected {
+                break;
+            }
+            collected = more;
+        }
+        for &(c, p) in sorted_keys.iter() {
+            if collected & key_bit(c) == 0 && flagged.contains(&c) == false {
+                problems.push(Problem::LockedKey(p, c));
+            }
+        }
 
-                        let id;
-                        if let Some(&idx) = node_to_vertex_map.get(&nodes) {
-                            // This one exists. Save its ID.
-                            id = idx;
-                        } else {
-                            // This one doesn't exist. Create it and save its ID.
-                            id = graph.vertices.len();
-                            node_to_vertex_map.insert(nodes.clone(), id);
-
-                            let v = Vertex {
-                                id,
-                                nodes,
-                                distance: u32::max_value(),
-                                pi: Some(graph.vertices[frontier_id].id),
-                                connected: Vec::new(),
-                            };
-                            graph.vertices.push(v);
-                            frontier.push(id);
-                        }
+        problems
+    }
 
-                        // Create an edge based on the saved ID and add it to the current node in the graph.
-                        let edge = Edge {
-                            vertex_id: id,
-                            distance,
-                        };
-                        graph.vertices[frontier_id].connected.push(edge);
-                    }
+    // The map with every problem cell drawn in reverse video
+    fn render_problems(&self, problems: &[Problem]) -> String {
+        let highlighted: HashSet<Point> = problems.iter().filter_map(|p| p.point()).collect();
+        let x_max = self.area.keys().map(|p| p.x).max().unwrap_or(0);
+        let y_max = self.area.keys().map(|p| p.y).max().unwrap_or(0);
+
+        let mut rendered = String::new();
+        for y in 0..=y_max {
+            for x in 0..=x_max {
+                let p = Point { x, y };
+                let c = self.area.get(&p).map_or(' ', |space| space.char());
+                if highlighted.contains(&p) == true {
+                    rendered.push_str(&format!("{}{}{}", HIGHLIGHT_ON, c, HIGHLIGHT_OFF));
+                } else {
+                    rendered.push(c);
                 }
             }
-
-            //println!("Graph frontier: {:?}", frontier);
-            if frontier.is_empty() == true {
-                break;
-            }
+            rendered.push('\n');
         }
+        rendered
+    }
 
-        //println!("Graph: {:#?}", graph);
-        graph
+    fn display_problems(&self, problems: &[Problem]) {
+        print!("{}", self.render_problems(problems));
+        for problem in problems {
+            println!("{}", problem);
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-struct Node {
-    point: Point,
-    space: Space,
-    inventory: u32,
+const HIGHLIGHT_ON: &str = "\x1b[7m";
+const HIGHLIGHT_OFF: &str = "\x1b[0m";
+
+#[derive(Debug, PartialEq)]
+enum Problem {
+    UnknownSpace(Point, char),
+    DuplicateKey(Point, char),
+    DoorWithoutKey(Point, char),
+    NoEntrance,
+    SeparateEntrance(Point),
+    UnreachableKey(Point, char),
+    KeyBehindOwnDoor(Point, char),
+    LockedKey(Point, char),
 }
 
-impl Node {
-    fn idx_from_char(c: char) -> usize {
-        if c.is_ascii_lowercase() == false {
-            panic!("Invalid character!");
+impl Problem {
+    fn point(&self) -> Option<Point> {
+        match *self {
+            Problem::UnknownSpace(p, _)
+            | Problem::DuplicateKey(p, _)
+            | Problem::DoorWithoutKey(p, _)
+            | Problem::SeparateEntrance(p)
+            | Problem::UnreachableKey(p, _)
+            | Problem::KeyBehindOwnDoor(p, _)
+            | Problem::LockedKey(p, _) => Some(p),
+            Problem::NoEntrance => None,
         }
-
-        let idx = (c as u8) - b'a';
-        idx as usize
     }
+}
 
-    fn has_key(&self, c: char) -> bool {
-        let idx = Node::idx_from_char(c);
-        let key_bit = self.inventory & (1 << idx);
-        key_bit != 0
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Problem::UnknownSpace(p, c) => write!(f, "({}, {}): unknown space {:?}", p.x, p.y, c),
+            Problem::DuplicateKey(p, c) => write!(f, "({}, {}): second key {}", p.x, p.y, c),
+            Problem::DoorWithoutKey(p, c) => write!(
+                f,
+                "({}, {}): door {} has no key",
+                p.x,
+                p.y,
+                c.to_ascii_uppercase()
+            ),
+            Problem::NoEntrance => write!(f, "no entrance"),
+            Problem::SeparateEntrance(p) => write!(
+                f,
+                "({}, {}): entrance can't reach the first entrance",
+                p.x, p.y
+            ),
+            Problem::UnreachableKey(p, c) => {
+                write!(f, "({}, {}): key {} can't be reached", p.x, p.y, c)
+            }
+            Problem::KeyBehindOwnDoor(p, c) => write!(
+                f,
+                "({}, {}): key {} is behind door {}",
+                p.x,
+                p.y,
+                c,
+                c.to_ascii_uppercase()
+            ),
+            Problem::LockedKey(p, c) => write!(
+                f,
+                "({}, {}): key {} is locked behind doors that never open",
+                p.x, p.y, c
+            ),
+        }
     }
+}
 
-    fn set_key(&mut self, c: char, value: bool) {
-        let idx = Node::idx_from_char(c);
-        let key_bit = if value == true { 1 } else { 0 };
-        self.inventory |= key_bit << idx;
-    }
+// Keys are numbered by letter, so a set of keys (or of the doors they open) fits in a u32
+fn key_bit(c: char) -> u32 {
+    1 << (c as u32 - 'a' as u32)
 }
 
-#[derive(Debug)]
-struct Edge {
-    vertex_id: usize,
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct KeyPath {
     distance: u32,
+    doors: u32,
 }
 
-#[derive(Debug)]
-struct Vertex {
-    id: usize, // Not technically needed but useful for debug
-    nodes: Vec<Node>,
-    distance: u32, // This is used purely for Dijkstra's, where u32::max_value() is used to indicate infinity distance
-    pi: Option<usize>, // The "parent" or the vertex from which the fastest path comes
-    connected: Vec<Edge>,
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct State {
+    // Where each robot is: an entrance while it hasn't moved, then the last key it collected
+    positions: Vec<usize>,
+    keys: u32,
 }
 
-struct Graph {
-    vertices: Vec<Vertex>,
+#[derive(Debug, PartialEq)]
+struct Route {
+    distance: u32,
+    // Each key in the order it was collected, with the robot that collected it
+    keys: Vec<(usize, char)>,
 }
 
-impl Graph {
-    fn dijkstra(&mut self, start_vertex: usize) {
-        // Set initial conditions:
-        //  - set all distances to infinity except the starting vertex which is set to 0
-        //  - set the starting vertex as the first vertex to process
-        for vertex in self.vertices.iter_mut() {
-            vertex.distance = u32::max_value();
-        }
-        self.vertices[start_vertex].distance = 0;
-
-        let mut visited: HashSet<usize> = HashSet::new();
-        let mut next: HashSet<usize> = HashSet::new();
-        next.insert(start_vertex);
-
-        while next.is_empty() == false {
-            // Find the next vertex with minimum distance. Mark it as visited
-            // (we're visiting it now) and remove it from the 'next' list.
-            let mut min_id = 0;
-            let mut min_value = u32::max_value();
-            for &vertex_id in next.iter() {
-                if self.vertices[vertex_id].distance < min_value {
-                    min_value = self.vertices[vertex_id].distance;
-                    min_id = vertex_id;
-                }
-            }
-            let current_vertex_id = min_id;
-            visited.insert(current_vertex_id);
-            next.remove(&min_id);
-            //println!("Dijkstra visit vertex #{}", current_vertex_id);
-
-            // Update distances of all adjacent vertices and add them to the 'next' list.
-            // Skip any vertices we've already visited.
-            let mut temp: Vec<(usize, u32, Option<usize>)> = Vec::new();
-            for e in self.vertices[current_vertex_id].connected.iter() {
-                if visited.get(&e.vertex_id) != None {
-                    continue;
-                }
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|(robot, key)| format!("{}:{}", robot, key))
+            .collect();
+        write!(f, "{} steps: {}", self.distance, keys.join(", "))
+    }
+}
 
-                let new_distance = self.vertices[current_vertex_id].distance + e.distance;
-                let old_distance = self.vertices[e.vertex_id].distance;
-                if new_distance < old_distance {
-                    temp.push((e.vertex_id, new_distance, Some(current_vertex_id)));
-                }
+// The distances between every entrance and key, searched over (robot positions, keys held)
+struct Vault {
+    keys: Vec<char>,
+    robots: usize,
+    // paths[from][to], where `from` counts the entrances first and then the keys, and `to` is a key
+    paths: Vec<Vec<Option<KeyPath>>>,
+}
 
-                next.insert(e.vertex_id);
-            }
+impl Vault {
+    fn from_map(map: &Map) -> Vault {
+        let entrances = map.get_entrances();
+        let keys = map.get_keys();
+        let paths = entrances
+            .iter()
+            .chain(keys.iter().map(|(_, p)| p))
+            .map(|&start| {
+                let found = map.paths_from(start);
+                keys.iter().map(|(c, _)| found.get(c).copied()).collect()
+            })
+            .collect();
 
-            for (vertex_id, distance, pi) in temp {
-                self.vertices[vertex_id].distance = distance;
-                self.vertices[vertex_id].pi = pi;
-            }
+        Vault {
+            keys: keys.iter().map(|&(c, _)| c).collect(),
+            robots: entrances.len(),
+            paths,
         }
     }
 
-    fn get_shortest_path(&self) -> (u32, Vec<Vec<Space>>) {
-        let mut longest_inventory = 0;
-        let mut shortest_distance = u32::max_value();
-        let mut shortest_end_vertex_id = 0;
-        for vertex in self.vertices.iter() {
-            let current_inventory = vertex.nodes[0].inventory; // All inventories are guaranteed to be the same; just use the first one.
-            if current_inventory > longest_inventory {
-                longest_inventory = current_inventory;
-                shortest_distance = u32::max_value();
-                shortest_end_vertex_id = 0;
+    fn all_keys(&self) -> u32 {
+        self.keys.iter().fold(0, |mask, &c| mask | key_bit(c))
+    }
+
+    // Dijkstra over states. Returns None if some key can never be collected.
+    fn solve(&self) -> Option<Route> {
+        let start = State {
+            positions: (0..self.robots).collect(),
+            keys: 0,
+        };
+        let mut distances: HashMap<State, u32> = HashMap::new();
+        let mut parents: HashMap<State, (State, usize, char)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        distances.insert(start.clone(), 0);
+        heap.push(Reverse((0, start.keys, start.positions.clone())));
+
+        while let Some(Reverse((distance, keys, positions))) = heap.pop() {
+            let state = State { positions, keys };
+            if distances.get(&state) != Some(&distance) {
+                continue;
+            }
+            if state.keys == self.all_keys() {
+                return Some(self.route(distance, &state, &parents));
             }
 
-            if vertex.distance < shortest_distance {
-                shortest_distance = vertex.distance;
-                shortest_end_vertex_id = vertex.id;
+            for (robot, &from) in state.positions.iter().enumerate() {
+                for (key_idx, path) in self.paths[from].iter().enumerate() {
+                    let bit = key_bit(self.keys[key_idx]);
+                    let path = match path {
+                        Some(path) if state.keys & bit == 0 && path.doors & !state.keys == 0 => {
+                            path
+                        }
+                        _ => continue,
+                    };
+
+                    let mut next = state.clone();
+                    next.positions[robot] = self.robots + key_idx;
+                    next.keys |= bit;
+                    let next_distance = distance + path.distance;
+                    let improved = match distances.get(&next) {
+                        Some(&d) => next_distance < d,
+                        None => true,
+                    };
+                    if improved == true {
+                        distances.insert(next.clone(), next_distance);
+                        parents.insert(next.clone(), (state.clone(), robot, self.keys[key_idx]));
+                        heap.push(Reverse((next_distance, next.keys, next.positions)));
+                    }
+                }
             }
         }
 
-        //println!("End vertex: {:?}", graph[shortest_end_vertex_id]);
+        None
+    }
 
-        let mut path: Vec<Vec<Space>> = Vec::new();
-        let mut current_vertex_id = shortest_end_vertex_id;
-        let spaces: Vec<Space> = self.vertices[current_vertex_id]
-            .nodes
-            .iter()
-            .map(|n| n.space)
-            .collect();
-        path.push(spaces);
-        while let Some(parent_id) = self.vertices[current_vertex_id].pi {
-            current_vertex_id = parent_id;
-            let spaces: Vec<Space> = self.vertices[current_vertex_id]
-                .nodes
-                .iter()
-                .map(|n| n.space)
-                .collect();
-            path.push(spaces);
+    fn route(
+        &self,
+        distance: u32,
+        end: &State,
+        parents: &HashMap<State, (State, usize, char)>,
+    ) -> Route {
+        let mut keys = Vec::new();
+        let mut state = end;
+        while let Some((parent, robot, key)) = parents.get(state) {
+            keys.push((*robot, *key));
+            state = parent;
         }
-        path.reverse();
-
-        (self.vertices[shortest_end_vertex_id].distance, path)
+        keys.reverse();
+        Route { distance, keys }
     }
 }
 
@@ -641,51 +743,32 @@ pub fn solve(input: &str) -> u32 {
     map.split_entrance(&entrance);
     map.display();
 
-    let mut graph = map.build_graph();
-
-    /*println!("Graph:");
-    println!("   {} vertices", graph.vertices.len());
-    for (id, vertex) in graph.vertices.iter().enumerate() {
-        println!("   Vertex {}:", id);
-        println!("      id: {}", vertex.id);
-        println!("      node.point: {:?}", vertex.node.point);
-        println!("      node.space: {:?}", vertex.node.space);
-        println!("      node.inventory: {}", vertex.node.inventory);
-        println!("      distance: {}", vertex.distance);
-        println!("      pi: {:?}", vertex.pi);
-        println!("      edges:");
-        for e in vertex.connected.iter() {
-            println!("         {:?}", e);
-        }
-        println!();
-    }*/
-
-    graph.dijkstra(0);
-
-    let (distance, path) = graph.get_shortest_path();
-    println!("Distance: {}", distance);
-    print!("Path: {:?}", path[0]);
-    for path_idx in 0..path.len() {
-        // Check what changed between this index and the previous one (if any) and print it
-        if path_idx > 0 {
-            let zip_iter = path[path_idx].iter().zip(path[path_idx - 1].iter());
-            for (&curr, &prev) in zip_iter {
-                if curr != prev {
-                    print!(", {:?}", curr);
-                }
-            }
-        }
+    let problems = map.validate(true);
+    if problems.is_empty() == false {
+        map.display_problems(&problems);
+        panic!("The vault can't be solved");
     }
-    println!();
-    distance
+
+    let route = Vault::from_map(&map)
+        .solve()
+        .expect("Some keys can't be reached");
+    println!("Route: {}", route);
+    route.distance
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn solve_map(input: &str) -> Route {
+        let mut map = Map::from_string(input);
+        let entrance = map.get_entrance();
+        map.split_entrance(&entrance);
+        Vault::from_map(&map).solve().unwrap()
+    }
+
     #[test]
-    fn test_get_shortest_path() {
+    fn test_vault_solve() {
         let input = "
 #######
 #a.#Cd#
@@ -695,48 +778,9 @@ mod test {
 #cB#Ab#
 #######
 ";
-        let mut map = Map::from_string(&input);
-        let entrance = map.get_entrance();
-        map.split_entrance(&entrance);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, path) = graph.get_shortest_path();
-        assert_eq!(distance, 8);
-        assert_eq!(
-            path,
-            [
-                [
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Key('b')
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Entrance,
-                    Space::Key('c'),
-                    Space::Key('b')
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Key('d'),
-                    Space::Key('c'),
-                    Space::Key('b')
-                ]
-            ]
-        );
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 8);
+        assert_eq!(route.keys, [(0, 'a'), (3, 'b'), (2, 'c'), (1, 'd')]);
 
         let input = "
 ###############
@@ -747,13 +791,8 @@ mod test {
 #b.....#.....c#
 ###############
 ";
-        let mut map = Map::from_string(&input);
-        let entrance = map.get_entrance();
-        map.split_entrance(&entrance);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, _path) = graph.get_shortest_path();
-        assert_eq!(distance, 24);
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 24);
         // There are multiple possible paths, don't verify
 
         let input = "
@@ -765,97 +804,121 @@ mod test {
 #fEbA.#.FgHi#
 #############
 ";
-        let mut map = Map::from_string(&input);
-        let entrance = map.get_entrance();
-        map.split_entrance(&entrance);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, path) = graph.get_shortest_path();
-        assert_eq!(distance, 32);
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 32);
         assert_eq!(
-            path,
+            route.keys,
             [
-                [
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Entrance,
-                    Space::Entrance,
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('a'),
-                    Space::Entrance,
-                    Space::Key('b'),
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('c'),
-                    Space::Entrance,
-                    Space::Key('b'),
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('c'),
-                    Space::Entrance,
-                    Space::Key('d'),
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Entrance,
-                    Space::Key('d'),
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Entrance,
-                    Space::Key('f'),
-                    Space::Entrance
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Entrance,
-                    Space::Key('f'),
-                    Space::Key('g')
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Key('h'),
-                    Space::Key('f'),
-                    Space::Key('g')
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Key('h'),
-                    Space::Key('f'),
-                    Space::Key('i')
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Key('j'),
-                    Space::Key('f'),
-                    Space::Key('i')
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Key('j'),
-                    Space::Key('f'),
-                    Space::Key('k')
-                ],
-                [
-                    Space::Key('e'),
-                    Space::Key('l'),
-                    Space::Key('f'),
-                    Space::Key('k')
-                ],
+                (0, 'a'),
+                (2, 'b'),
+                (0, 'c'),
+                (2, 'd'),
+                (0, 'e'),
+                (2, 'f'),
+                (3, 'g'),
+                (1, 'h'),
+                (3, 'i'),
+                (1, 'j'),
+                (3, 'k'),
+                (1, 'l'),
+            ]
+        );
+
+        let input = "
+#############
+#g#f.D#..h#l#
+#F###e#E###.#
+#dCba...BcIJ#
+#####.@.#####
+#nK.L...G...#
+#M###N#H###.#
+#o#m..#i#jk.#
+#############
+";
+        let route = solve_map(&input);
+        assert_eq!(route.distance, 72);
+        // There are multiple possible paths, don't verify
+    }
+
+    #[test]
+    fn test_vault_single_robot() {
+        let map = Map::from_string("#########\n#b.A.@.a#\n#########");
+        let route = Vault::from_map(&map).solve().unwrap();
+        assert_eq!(route.distance, 8);
+        assert_eq!(route.to_string(), "8 steps: 0:a, 0:b");
+
+        let map = Map::from_string(
+            "\
+########################
+#f.D.E.e.C.b.A.@.a.B.c.#
+######################.#
+#d.....................#
+########################",
+        );
+        let route = Vault::from_map(&map).solve().unwrap();
+        assert_eq!(route.distance, 86);
+        let keys: String = route.keys.iter().map(|&(_, key)| key).collect();
+        assert_eq!(keys, "abcdef");
+
+        // Nothing opens door A
+        let map = Map::from_string("######\n#@.Ab#\n######");
+        assert_eq!(Vault::from_map(&map).solve(), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let map = Map::from_string(
+            "\
+###########
+#@aa.DdFE?#
+###########
+#e........#
+###########",
+        );
+        assert_eq!(
+            map.validate(false),
+            vec![
+                Problem::DuplicateKey(Point { x: 3, y: 1 }, 'a'),
+                Problem::UnknownSpace(Point { x: 9, y: 1 }, '?'),
+                Problem::DoorWithoutKey(Point { x: 7, y: 1 }, 'f'),
+                Problem::KeyBehindOwnDoor(Point { x: 6, y: 1 }, 'd'),
+                Problem::UnreachableKey(Point { x: 1, y: 3 }, 'e'),
             ]
         );
 
+        // Each key waits behind the other's door
+        let map = Map::from_string("#bA@Ba#");
+        let problems = map.validate(false);
+        assert_eq!(
+            problems,
+            vec![
+                Problem::LockedKey(Point { x: 5, y: 0 }, 'a'),
+                Problem::LockedKey(Point { x: 1, y: 0 }, 'b'),
+            ]
+        );
+        assert_eq!(
+            map.render_problems(&problems),
+            "#\x1b[7mb\x1b[0mA@B\x1b[7ma\x1b[0m#\n"
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "(5, 0): key a is locked behind doors that never open"
+        );
+
+        let map = Map::from_string("#@#@#");
+        assert_eq!(
+            map.validate(false),
+            vec![Problem::SeparateEntrance(Point { x: 3, y: 0 })]
+        );
+        assert_eq!(map.validate(true), vec![]);
+        assert_eq!(
+            Map::from_string("#a#").validate(true),
+            vec![Problem::NoEntrance]
+        );
+    }
+
+    #[test]
+    fn test_validate_examples() {
         let input = "
 #############
 #g#f.D#..h#l#
@@ -867,13 +930,11 @@ mod test {
 #o#m..#i#jk.#
 #############
 ";
-        let mut map = Map::from_string(&input);
+        let mut map = Map::from_string(input);
+        assert_eq!(map.validate(false), vec![]);
         let entrance = map.get_entrance();
         map.split_entrance(&entrance);
-        let mut graph = map.build_graph();
-        graph.dijkstra(0);
-        let (distance, _path) = graph.get_shortest_path();
-        assert_eq!(distance, 72);
-        // There are multiple possible paths, don't verify
+        assert_eq!(map.validate(true), vec![]);
+        assert_eq!(map.validate(false).len(), 3);
     }
 }
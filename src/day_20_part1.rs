@@ -75,8 +75,11 @@
     In your maze, how many steps does it take to get from the open tile marked AA to the open tile marked ZZ?
 */
 
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -158,9 +161,96 @@ impl Space {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 struct Portal {
-    value: [char; 2],
+    name: String,
+    next_depth: i32,
+}
+
+#[derive(Debug, PartialEq)]
+enum MazeError {
+    UnknownChar(Point, char),
+    // A letter that isn't part of any label next to an open space
+    StrayLetter(Point),
+    // An open space with more than one label next to it
+    CrowdedSpace(Point),
+    // How many times a label was found, where AA and ZZ need one and everything else two
+    LabelCount(String, usize),
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::UnknownChar(p, c) => {
+                write!(f, "Unknown character {:?} at ({}, {})", c, p.x, p.y)
+            }
+            MazeError::StrayLetter(p) => {
+                write!(f, "Letter at ({}, {}) is not part of a label", p.x, p.y)
+            }
+            MazeError::CrowdedSpace(p) => {
+                write!(f, "Space at ({}, {}) has more than one label", p.x, p.y)
+            }
+            MazeError::LabelCount(name, count) => {
+                write!(f, "Label {} appears {} times", name, count)
+            }
+        }
+    }
+}
+
+// How stepping through a portal changes the level
+#[derive(Clone, Debug, PartialEq)]
+enum DepthPolicy {
+    // Every portal stays on the same level
+    Flat,
+    // Inner portals go one level deeper and outer portals one level up, down to `max_depth`
+    Recursive { max_depth: i32 },
+    // The change for the portal entered at each point. Portals not listed keep the level.
+    Custom(HashMap<Point, i32>),
+}
+
+impl DepthPolicy {
+    // The level after stepping through the portal at `point`, if that level can be entered.
+    // Level 0 is the outermost.
+    fn next_level(&self, point: Point, portal: &Portal, level: i32) -> Option<i32> {
+        let next_level = match self {
+            DepthPolicy::Flat => level,
+            DepthPolicy::Recursive { max_depth } => {
+                Some(level + portal.next_depth).filter(|l| l <= max_depth)?
+            }
+            DepthPolicy::Custom(deltas) => level + deltas.get(&point).copied().unwrap_or(0),
+        };
+        Some(next_level).filter(|&l| l >= 0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Hop {
+    portal: String,
+    // The level on the far side of the portal, and the steps taken once there
+    level: i32,
+    steps: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Route {
+    steps: u32,
+    hops: Vec<Hop>,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AA")?;
+        for hop in self.hops.iter() {
+            write!(f, " -> {} ({})", hop.portal, hop.level)?;
+        }
+        write!(f, " -> ZZ: {} steps", self.steps)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Node {
+    point: Point,
+    depth: i32,
 }
 
 #[derive(Clone)]
@@ -170,7 +260,7 @@ struct Map {
 }
 
 impl Map {
-    fn from_string(input: &str) -> Map {
+    fn from_string(input: &str) -> Result<Map, MazeError> {
         let mut area = HashMap::new();
 
         let mut p = Point { x: 0, y: 0 };
@@ -179,12 +269,10 @@ impl Map {
                 let space = Space::from_value(c);
                 if space != Space::Unknown {
                     area.insert(p, space);
-                    p.x += 1;
-                } else if c == ' ' {
-                    p.x += 1;
-                } else {
-                    panic!("Unknown input: 0x{:02x}", c as u8);
+                } else if c != ' ' {
+                    return Err(MazeError::UnknownChar(p, c));
                 }
+                p.x += 1;
             }
             p.x = 0;
             p.y += 1;
@@ -194,71 +282,110 @@ impl Map {
             area,
             portals: HashMap::new(),
         };
-        map.detect_portals();
+        map.detect_portals()?;
         //println!("Portals: {:#?}", map.portals);
 
-        map
+        Ok(map)
     }
 
-    fn detect_portals(&mut self) {
-        let mut x_range = (0, 0);
-        let mut y_range = (0, 0);
-
-        for k in self.area.keys() {
-            if k.x < x_range.0 {
-                x_range = (k.x, x_range.1);
-            } else if k.x > x_range.1 {
-                x_range = (x_range.0, k.x);
-            }
+    // Labels run in a straight line away from the open space they mark, in any direction, and
+    // read left to right or top to bottom. Portals on the outside edge of the donut lead up a
+    // level and the rest lead down, except for AA and ZZ which go nowhere.
+    fn detect_portals(&mut self) -> Result<(), MazeError> {
+        let maze: Vec<Point> = self
+            .area
+            .iter()
+            .filter(|(_, &space)| space == Space::Empty || space == Space::Wall)
+            .map(|(&p, _)| p)
+            .collect();
+        let x_range = (
+            maze.iter().map(|p| p.x).min().unwrap_or(0),
+            maze.iter().map(|p| p.x).max().unwrap_or(0),
+        );
+        let y_range = (
+            maze.iter().map(|p| p.y).min().unwrap_or(0),
+            maze.iter().map(|p| p.y).max().unwrap_or(0),
+        );
+
+        let mut spaces: Vec<Point> = self
+            .area
+            .iter()
+            .filter(|(_, &space)| space == Space::Empty)
+            .map(|(&p, _)| p)
+            .collect();
+        spaces.sort_by_key(|p| (p.y, p.x));
+
+        let mut used: HashSet<Point> = HashSet::new();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for space in spaces {
+            let candidates = [
+                Cardinal::North,
+                Cardinal::South,
+                Cardinal::West,
+                Cardinal::East,
+            ];
+            for direction in candidates.iter() {
+                let mut letters = Vec::new();
+                let mut location = direction.step_from(space);
+                while let Some(Space::PortalPiece(c)) = self.area.get(&location) {
+                    letters.push(*c);
+                    used.insert(location);
+                    location = direction.step_from(location);
+                }
+                if letters.is_empty() == true {
+                    continue;
+                }
+                if let Cardinal::North | Cardinal::West = direction {
+                    letters.reverse();
+                }
 
-            if k.y < y_range.0 {
-                y_range = (k.y, y_range.1);
-            } else if k.y > y_range.1 {
-                y_range = (y_range.0, k.y);
+                let name: String = letters.into_iter().collect();
+                let outer = space.x == x_range.0
+                    || space.x == x_range.1
+                    || space.y == y_range.0
+                    || space.y == y_range.1;
+                let next_depth = match name.as_str() {
+                    "AA" | "ZZ" => 0,
+                    _ if outer == true => -1,
+                    _ => 1,
+                };
+                *counts.entry(name.clone()).or_insert(0) += 1;
+                let portal = Portal { name, next_depth };
+                if self.portals.insert(space, portal).is_some() {
+                    return Err(MazeError::CrowdedSpace(space));
+                }
             }
         }
-        // println!("x_range: {:?}", x_range);
-        // println!("y_range: {:?}", y_range);
-
-        for y in y_range.0..=y_range.1 {
-            for x in x_range.0..=x_range.1 {
-                if let Some(Space::PortalPiece(c0)) = self.area.get(&Point { x, y }) {
-                    let portal_piece0 = *c0;
-
-                    // We are scanning from left to right, top to bottom. So we only need to look to the right and down.
-                    // If there's an adjacent letter in any other position, this is part of an existing portal.
-                    let portal_piece1;
-                    if let Some(Space::PortalPiece(c1)) = self.area.get(&Point { x: x + 1, y }) {
-                        portal_piece1 = *c1;
-                    } else if let Some(Space::PortalPiece(c1)) =
-                        self.area.get(&Point { x, y: y + 1 })
-                    {
-                        portal_piece1 = *c1;
-                    } else {
-                        continue; // Not a valid portal
-                    }
 
-                    // Find the walkable space. Relative to the first piece it is either one left, two right, one up, or two down.
-                    let portal_walkable;
-                    if let Some(Space::Empty) = self.area.get(&Point { x: x - 1, y }) {
-                        portal_walkable = Point { x: x - 1, y };
-                    } else if let Some(Space::Empty) = self.area.get(&Point { x: x + 2, y }) {
-                        portal_walkable = Point { x: x + 2, y };
-                    } else if let Some(Space::Empty) = self.area.get(&Point { x, y: y - 1 }) {
-                        portal_walkable = Point { x, y: y - 1 };
-                    } else if let Some(Space::Empty) = self.area.get(&Point { x, y: y + 2 }) {
-                        portal_walkable = Point { x, y: y + 2 };
-                    } else {
-                        continue; // Not a valid portal
-                    }
+        let mut letters: Vec<Point> = self
+            .area
+            .iter()
+            .filter(|(p, space)| {
+                matches!(space, Space::PortalPiece(_)) && used.contains(p) == false
+            })
+            .map(|(&p, _)| p)
+            .collect();
+        letters.sort_by_key(|p| (p.y, p.x));
+        if let Some(&p) = letters.first() {
+            return Err(MazeError::StrayLetter(p));
+        }
 
-                    let portal = Portal {
-                        value: [portal_piece0, portal_piece1],
-                    };
-                    self.portals.insert(portal_walkable, portal);
-                }
+        for end in ["AA", "ZZ"].iter() {
+            counts.entry(end.to_string()).or_insert(0);
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort();
+        for (name, count) in counts {
+            let expected = match name.as_str() {
+                "AA" | "ZZ" => 1,
+                _ => 2,
+            };
+            if count != expected {
+                return Err(MazeError::LabelCount(name, count));
             }
         }
+
+        Ok(())
     }
 
     fn display(&self) {
@@ -296,28 +423,46 @@ impl Map {
     }
 
     fn get_entrance(&self) -> Point {
-        for (&k, &v) in self.portals.iter() {
-            if let ['A', 'A'] = v.value {
+        for (&k, v) in self.portals.iter() {
+            if v.name == "AA" {
                 return k;
             }
         }
         panic!("No entrance found!");
     }
 
-    fn a_to_z(&self) -> u32 {
-        let entrance = self.get_entrance();
-        //println!("Entrance: {:?}", entrance);
-
-        let mut frontier: Vec<Point> = Vec::new();
-        let mut walked: HashSet<Point> = HashSet::new();
+    fn get_exit(&self) -> Point {
+        for (&k, v) in self.portals.iter() {
+            if v.name == "ZZ" {
+                return k;
+            }
+        }
+        panic!("No exit found!");
+    }
 
-        frontier.push(entrance);
-        walked.insert(entrance);
+    // The other end of the portal at this point
+    fn partner(&self, point: Point) -> Option<Point> {
+        let portal = self.portals.get(&point)?;
+        self.portals
+            .iter()
+            .find(|(&k, v)| k != point && v.name == portal.name)
+            .map(|(&k, _)| k)
+    }
 
-        let mut steps = 0;
-        loop {
-            steps += 1;
-            for location in frontier.drain(..).collect::<Vec<Point>>() {
+    // Walking distances between the portals, without going through any of them
+    fn portal_graph(&self) -> HashMap<Point, Vec<(Point, u32)>> {
+        let mut graph = HashMap::new();
+        for &start in self.portals.keys() {
+            let mut edges = Vec::new();
+            let mut walked: HashSet<Point> = HashSet::new();
+            let mut frontier: VecDeque<(Point, u32)> = VecDeque::new();
+            walked.insert(start);
+            frontier.push_back((start, 0));
+
+            while let Some((location, steps)) = frontier.pop_front() {
+                if location != start && self.portals.contains_key(&location) == true {
+                    edges.push((location, steps));
+                }
                 let candidates = [
                     Cardinal::North,
                     Cardinal::South,
@@ -326,62 +471,198 @@ impl Map {
                 ];
                 for direction in candidates.iter() {
                     let step_in_direction = direction.step_from(location);
-                    //println!("Step: {:?}", step_in_direction);
-                    if walked.get(&step_in_direction) == None {
-                        // Step into any adjacent empty space
-                        if self.area.get(&step_in_direction) == Some(&Space::Empty) {
-                            // If this is the goal space, return now
-                            if let Some(&portal) = self.portals.get(&step_in_direction) {
-                                if portal.value == ['Z', 'Z'] {
-                                    return steps;
-                                }
-                            }
-
-                            // Otherwise, add it to the frontier
-                            frontier.push(step_in_direction);
-                            walked.insert(step_in_direction);
-                        }
+                    if self.area.get(&step_in_direction) == Some(&Space::Empty)
+                        && walked.insert(step_in_direction) == true
+                    {
+                        frontier.push_back((step_in_direction, steps + 1));
                     }
                 }
+            }
+            graph.insert(start, edges);
+        }
+        graph
+    }
 
-                // Also step into any portals connected to the current space
-                if let Some(&portal) = self.portals.get(&location) {
-                    // Find matching portal
-                    for (&k, &v) in self.portals.iter() {
-                        if k != location && v.value == portal.value && walked.get(&k) == None {
-                            frontier.push(k);
-                            walked.insert(k);
-                        }
-                    }
+    // Dijkstra over (portal, level) from AA to ZZ on level 0
+    fn route(&self, policy: &DepthPolicy) -> Option<Route> {
+        let graph = self.portal_graph();
+        let start = Node {
+            point: self.get_entrance(),
+            depth: 0,
+        };
+        let exit = Node {
+            point: self.get_exit(),
+            depth: 0,
+        };
+
+        let mut distances: HashMap<Node, u32> = HashMap::new();
+        let mut parents: HashMap<Node, (Node, bool)> = HashMap::new();
+        let mut heap = BinaryHeap::new();
+        distances.insert(start, 0);
+        heap.push(Reverse((0, start.depth, start.point.x, start.point.y)));
+
+        while let Some(Reverse((steps, level, x, y))) = heap.pop() {
+            let node = Node {
+                point: Point { x, y },
+                depth: level,
+            };
+            if distances.get(&node) != Some(&steps) {
+                continue;
+            }
+            if node == exit {
+                return Some(self.hops(steps, exit, &distances, &parents));
+            }
+
+            // Walk to another portal on this level, or go through this one
+            let mut moves: Vec<(Node, u32, bool)> = graph[&node.point]
+                .iter()
+                .map(|&(point, distance)| {
+                    (
+                        Node {
+                            point,
+                            depth: level,
+                        },
+                        distance,
+                        false,
+                    )
+                })
+                .collect();
+            if let Some(partner) = self.partner(node.point) {
+                let portal = &self.portals[&node.point];
+                if let Some(next_level) = policy.next_level(node.point, portal, level) {
+                    let next = Node {
+                        point: partner,
+                        depth: next_level,
+                    };
+                    moves.push((next, 1, true));
                 }
             }
 
-            //println!("frontier: {:?}", frontier);
-            if frontier.is_empty() == true {
-                break;
+            for (next, distance, through_portal) in moves {
+                let next_steps = steps + distance;
+                let improved = match distances.get(&next) {
+                    Some(&d) => next_steps < d,
+                    None => true,
+                };
+                if improved == true {
+                    distances.insert(next, next_steps);
+                    parents.insert(next, (node, through_portal));
+                    heap.push(Reverse((
+                        next_steps,
+                        next.depth,
+                        next.point.x,
+                        next.point.y,
+                    )));
+                }
             }
         }
 
-        panic!("Could not find portal ZZ");
+        None
+    }
+
+    fn hops(
+        &self,
+        steps: u32,
+        exit: Node,
+        distances: &HashMap<Node, u32>,
+        parents: &HashMap<Node, (Node, bool)>,
+    ) -> Route {
+        let mut hops = Vec::new();
+        let mut node = exit;
+        while let Some(&(parent, through_portal)) = parents.get(&node) {
+            if through_portal == true {
+                hops.push(Hop {
+                    portal: self.portals[&node.point].name.clone(),
+                    level: node.depth,
+                    steps: distances[&node],
+                });
+            }
+            node = parent;
+        }
+        hops.reverse();
+        Route { steps, hops }
     }
 }
 
 #[aoc(day20, part1)]
 pub fn solve(input: &str) -> u32 {
-    let map = Map::from_string(&input);
+    let map = match Map::from_string(input) {
+        Ok(map) => map,
+        Err(e) => panic!("{}", e),
+    };
     //map.display();
 
-    let steps = map.a_to_z();
-    println!("Steps: {}", steps);
-    steps
+    let route = map
+        .route(&DepthPolicy::Flat)
+        .expect("Could not find portal ZZ");
+    println!("Route: {}", route);
+    route.steps
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    const LONG_LABELS: &str = "
+      A
+      A
+   ###.###
+PQR.....##
+   ###.###
+   ###....ZZ
+   ###.###
+      P
+      Q
+      R
+";
+
+    #[test]
+    fn test_parse() {
+        let map = Map::from_string(LONG_LABELS).unwrap();
+        let mut portals: Vec<(Point, String, i32)> = map
+            .portals
+            .iter()
+            .map(|(&p, portal)| (p, portal.name.clone(), portal.next_depth))
+            .collect();
+        portals.sort_by_key(|(p, _, _)| (p.y, p.x));
+        assert_eq!(
+            portals,
+            vec![
+                (Point { x: 6, y: 3 }, "AA".to_string(), 0),
+                (Point { x: 3, y: 4 }, "PQR".to_string(), -1),
+                (Point { x: 9, y: 6 }, "ZZ".to_string(), 0),
+                (Point { x: 6, y: 7 }, "PQR".to_string(), -1),
+            ]
+        );
+        assert_eq!(map.route(&DepthPolicy::Flat).unwrap().steps, 6);
+
+        let input = LONG_LABELS.replace("PQR.", "   .");
+        assert_eq!(
+            Map::from_string(&input).err(),
+            Some(MazeError::LabelCount("PQR".to_string(), 1))
+        );
+
+        let input = LONG_LABELS.replace("      A\n      A", "      A\nX     A");
+        assert_eq!(
+            Map::from_string(&input).err(),
+            Some(MazeError::StrayLetter(Point { x: 0, y: 2 }))
+        );
+
+        let input = LONG_LABELS.replace("....ZZ", "..?.ZZ");
+        assert_eq!(
+            Map::from_string(&input).err(),
+            Some(MazeError::UnknownChar(Point { x: 8, y: 6 }, '?'))
+        );
+
+        let input = LONG_LABELS.replace("   ###.###\n      P", "   #ZZ.###\n      P");
+        assert_eq!(
+            Map::from_string(&input).err(),
+            Some(MazeError::CrowdedSpace(Point { x: 6, y: 7 }))
+        );
+    }
+
     #[test]
-    fn test_a_to_z() {
+    fn test_route() {
         let input = "
          A
          A
@@ -403,9 +684,13 @@ FG..#########.....#
              Z
              Z
 ";
-        let map = Map::from_string(&input);
-        let steps = map.a_to_z();
-        assert_eq!(steps, 23);
+        let map = Map::from_string(&input).unwrap();
+        let route = map.route(&DepthPolicy::Flat).unwrap();
+        assert_eq!(route.steps, 23);
+        assert_eq!(
+            route.to_string(),
+            "AA -> BC (0) -> DE (0) -> FG (0) -> ZZ: 23 steps"
+        );
 
         let input = "
                    A
@@ -446,8 +731,10 @@ YN......#               VT..#....QG
            B   J   C
            U   P   P
 ";
-        let map = Map::from_string(&input);
-        let steps = map.a_to_z();
-        assert_eq!(steps, 58);
+        let map = Map::from_string(&input).unwrap();
+        let route = map.route(&DepthPolicy::Flat).unwrap();
+        assert_eq!(route.steps, 58);
+        let hops: Vec<&str> = route.hops.iter().map(|h| h.portal.as_str()).collect();
+        assert_eq!(hops, ["AS", "QG", "BU", "JO"]);
     }
 }
@@ -15,6 +15,7 @@
 */
 
 use std::collections::HashMap;
+use std::fmt;
 
 struct Program {
     code: Vec<i64>,
@@ -293,6 +294,377 @@ impl Program {
     }
 }
 
+const MAX_INSTRUCTIONS: usize = 15;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    fn last_sensor(&self) -> char {
+        match *self {
+            Self::Walk => 'D',
+            Self::Run => 'I',
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Walk => write!(f, "WALK"),
+            Self::Run => write!(f, "RUN"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CompileError {
+    UnexpectedChar(usize, char),
+    UnexpectedEnd,
+    UnknownSensor(usize, char),
+    TooComplex,
+    TooLong(usize),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar(pos, c) => write!(f, "unexpected '{}' at position {}", c, pos),
+            Self::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            Self::UnknownSensor(pos, c) => write!(f, "unknown sensor '{}' at position {}", c, pos),
+            Self::TooComplex => write!(f, "expression needs more than two registers"),
+            Self::TooLong(count) => write!(
+                f,
+                "expression needs {} instructions, limit is {}",
+                count, MAX_INSTRUCTIONS
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Sensor(char),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    fn is_literal(&self) -> bool {
+        match self {
+            Self::Sensor(_) => true,
+            Self::Not(inner) => matches!(**inner, Self::Sensor(_)),
+            _ => false,
+        }
+    }
+
+    fn eval(&self, sensors: &[bool]) -> bool {
+        match self {
+            Self::Sensor(c) => sensors[sensor_idx(*c)],
+            Self::Not(inner) => !inner.eval(sensors),
+            Self::And(terms) => terms.iter().all(|t| t.eval(sensors)),
+            Self::Or(terms) => terms.iter().any(|t| t.eval(sensors)),
+        }
+    }
+
+    fn negate(&self) -> Self {
+        match self {
+            Self::Not(inner) => (**inner).clone(),
+            _ => Self::Not(Box::new(self.clone())),
+        }
+    }
+}
+
+// Grammar, loosest binding first:
+//   or     := and ('|' and)*
+//   and    := unary ('&' unary)*
+//   unary  := ('!' | '~') unary | '(' or ')' | sensor
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    last_sensor: char,
+}
+
+impl Parser {
+    fn parse(string: &str, mode: Mode) -> Result<Expr, CompileError> {
+        let mut parser = Self {
+            chars: string.chars().collect(),
+            pos: 0,
+            last_sensor: mode.last_sensor(),
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            Some(c) => Err(CompileError::UnexpectedChar(parser.pos, c)),
+            None => Ok(expr),
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() == true {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    // (A & B) & C is the same gate as A & B & C, and flat gates leave the compiler more freedom
+    // over which term to compute first
+    fn push_term(terms: &mut Vec<Expr>, term: Expr, op: Op) {
+        match (term, op) {
+            (Expr::And(inner), Op::And) | (Expr::Or(inner), Op::Or) => terms.extend(inner),
+            (term, _) => terms.push(term),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, CompileError> {
+        let mut terms = Vec::new();
+        Self::push_term(&mut terms, self.parse_and()?, Op::Or);
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            Self::push_term(&mut terms, self.parse_and()?, Op::Or);
+        }
+
+        if terms.len() == 1 {
+            Ok(terms.remove(0))
+        } else {
+            Ok(Expr::Or(terms))
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, CompileError> {
+        let mut terms = Vec::new();
+        Self::push_term(&mut terms, self.parse_unary()?, Op::And);
+        while self.peek() == Some('&') {
+            self.pos += 1;
+            Self::push_term(&mut terms, self.parse_unary()?, Op::And);
+        }
+
+        if terms.len() == 1 {
+            Ok(terms.remove(0))
+        } else {
+            Ok(Expr::And(terms))
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, CompileError> {
+        let next = self.peek();
+        let pos = self.pos;
+        match next {
+            Some('!') | Some('~') => {
+                self.pos += 1;
+                Ok(self.parse_unary()?.negate())
+            }
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    Some(c) => Err(CompileError::UnexpectedChar(self.pos, c)),
+                    None => Err(CompileError::UnexpectedEnd),
+                }
+            }
+            Some(c) if c.is_ascii_uppercase() == true => {
+                self.pos += 1;
+                if c <= self.last_sensor {
+                    Ok(Expr::Sensor(c))
+                } else {
+                    Err(CompileError::UnknownSensor(pos, c))
+                }
+            }
+            Some(c) => Err(CompileError::UnexpectedChar(self.pos, c)),
+            None => Err(CompileError::UnexpectedEnd),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    And,
+    Or,
+    Not,
+}
+
+impl Op {
+    fn dual(&self) -> Self {
+        match *self {
+            Self::And => Self::Or,
+            Self::Or => Self::And,
+            Self::Not => Self::Not,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Instruction {
+    op: Op,
+    x: char,
+    y: char,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op_str = match self.op {
+            Op::And => "AND",
+            Op::Or => "OR",
+            Op::Not => "NOT",
+        };
+        write!(f, "{} {} {}", op_str, self.x, self.y)
+    }
+}
+
+fn other_register(reg: char) -> char {
+    if reg == 'T' {
+        'J'
+    } else {
+        'T'
+    }
+}
+
+#[derive(Clone)]
+struct Emitter {
+    instructions: Vec<Instruction>,
+}
+
+impl Emitter {
+    fn new() -> Self {
+        Self {
+            instructions: Vec::new(),
+        }
+    }
+
+    fn emit(&mut self, op: Op, x: char, y: char) {
+        self.instructions.push(Instruction { op, x, y });
+    }
+
+    // Registers start out false, so until the first write one can be loaded with a single OR
+    fn is_clear(&self, reg: char) -> bool {
+        self.instructions.iter().all(|i| i.y != reg)
+    }
+
+    fn load_literal(&mut self, expr: &Expr, reg: char) {
+        match expr {
+            Expr::Sensor(s) if self.is_clear(reg) == true => self.emit(Op::Or, *s, reg),
+            Expr::Sensor(s) => {
+                self.emit(Op::Not, *s, reg);
+                self.emit(Op::Not, reg, reg);
+            }
+            Expr::Not(inner) => match **inner {
+                Expr::Sensor(s) => self.emit(Op::Not, s, reg),
+                _ => panic!("Not a literal: {:?}", expr),
+            },
+            _ => panic!("Not a literal: {:?}", expr),
+        }
+    }
+
+    fn apply_literal(&mut self, op: Op, expr: &Expr, reg: char, scratch: bool) {
+        match expr {
+            Expr::Sensor(s) => self.emit(op, *s, reg),
+            Expr::Not(inner) => match **inner {
+                Expr::Sensor(s) if scratch == true => {
+                    let other = other_register(reg);
+                    self.emit(Op::Not, s, other);
+                    self.emit(op, other, reg);
+                }
+                Expr::Sensor(s) => {
+                    // x & !s == !(!x | s), which doesn't need a second register
+                    self.emit(Op::Not, reg, reg);
+                    self.emit(op.dual(), s, reg);
+                    self.emit(Op::Not, reg, reg);
+                }
+                _ => panic!("Not a literal: {:?}", expr),
+            },
+            _ => panic!("Not a literal: {:?}", expr),
+        }
+    }
+}
+
+fn shortest(a: Option<Emitter>, b: Option<Emitter>) -> Option<Emitter> {
+    match (a, b) {
+        (Some(a), Some(b)) if b.instructions.len() < a.instructions.len() => Some(b),
+        (Some(a), _) => Some(a),
+        (None, b) => b,
+    }
+}
+
+// Leaves the value of expr in reg. The other register may only be clobbered if scratch is set.
+fn compile_expr(expr: &Expr, emitter: &Emitter, reg: char, scratch: bool) -> Option<Emitter> {
+    match expr {
+        _ if expr.is_literal() == true => {
+            let mut emitter = emitter.clone();
+            emitter.load_literal(expr, reg);
+            Some(emitter)
+        }
+        Expr::Not(inner) => {
+            let mut emitter = compile_expr(inner, emitter, reg, scratch)?;
+            emitter.emit(Op::Not, reg, reg);
+            Some(emitter)
+        }
+        Expr::And(terms) | Expr::Or(terms) => {
+            let op = if let Expr::And(_) = expr {
+                Op::And
+            } else {
+                Op::Or
+            };
+            let direct = compile_gate(op, terms, emitter, reg, scratch);
+
+            // De Morgan: a & b == !(!a | !b)
+            let negated_terms: Vec<Expr> = terms.iter().map(|t| t.negate()).collect();
+            let flipped =
+                compile_gate(op.dual(), &negated_terms, emitter, reg, scratch).map(|mut e| {
+                    e.emit(Op::Not, reg, reg);
+                    e
+                });
+
+            shortest(direct, flipped)
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn compile_gate(
+    op: Op,
+    terms: &[Expr],
+    emitter: &Emitter,
+    reg: char,
+    scratch: bool,
+) -> Option<Emitter> {
+    let mut best = None;
+    for first in 0..terms.len() {
+        // The first term is computed directly in reg, everything else is combined into it
+        let mut candidate = compile_expr(&terms[first], emitter, reg, scratch);
+        for (i, term) in terms.iter().enumerate() {
+            if i == first {
+                continue;
+            }
+
+            candidate = candidate.and_then(|mut e| {
+                if term.is_literal() == true {
+                    e.apply_literal(op, term, reg, scratch);
+                    Some(e)
+                } else if scratch == true {
+                    // The other register is free again once the previous term has been folded
+                    // into reg, and load_literal clears it before reusing it
+                    let other = other_register(reg);
+                    let mut e = compile_expr(term, &e, other, false)?;
+                    e.emit(op, other, reg);
+                    Some(e)
+                } else {
+                    None
+                }
+            });
+        }
+        best = shortest(best, candidate);
+    }
+    best
+}
+
+#[derive(Debug)]
 struct Script {
     lines: Vec<String>,
 }
@@ -302,12 +674,164 @@ impl Script {
         Self { lines: Vec::new() }
     }
 
+    fn compile(expression: &str, mode: Mode) -> Result<Self, CompileError> {
+        let expr = Parser::parse(expression, mode)?;
+        Self::from_expr(&expr, mode)
+    }
+
+    fn from_expr(expr: &Expr, mode: Mode) -> Result<Self, CompileError> {
+        let emitter =
+            compile_expr(expr, &Emitter::new(), 'J', true).ok_or(CompileError::TooComplex)?;
+        if emitter.instructions.len() > MAX_INSTRUCTIONS {
+            return Err(CompileError::TooLong(emitter.instructions.len()));
+        }
+
+        let mut lines: Vec<String> = emitter.instructions.iter().map(|i| i.to_string()).collect();
+        lines.push(mode.to_string());
+        Ok(Self { lines })
+    }
+
     fn add_line(&mut self, line: &str) {
         println!("{}", line);
         self.lines.push(line.to_owned());
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum ScriptError {
+    BadInstruction(usize, String),
+    MissingMode,
+    TooLong(usize),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadInstruction(line, text) => {
+                write!(f, "bad instruction on line {}: {}", line, text)
+            }
+            Self::MissingMode => write!(f, "script must end with WALK or RUN"),
+            Self::TooLong(count) => write!(
+                f,
+                "script has {} instructions, limit is {}",
+                count, MAX_INSTRUCTIONS
+            ),
+        }
+    }
+}
+
+fn sensor_idx(c: char) -> usize {
+    (c as u8 - b'A') as usize
+}
+
+fn is_readable(c: char, mode: Mode) -> bool {
+    ('A'..=mode.last_sensor()).contains(&c) || c == 'T' || c == 'J'
+}
+
+fn read_hull(hull: &[bool], idx: usize) -> bool {
+    // Past the end of the known hull is assumed to be solid
+    hull.get(idx).copied().unwrap_or(true)
+}
+
+impl Instruction {
+    fn from_string(string: &str, mode: Mode) -> Option<Self> {
+        let parts: Vec<&str> = string.split_whitespace().collect();
+        if parts.len() != 3 {
+            return None;
+        }
+
+        let op = match parts[0] {
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "NOT" => Op::Not,
+            _ => return None,
+        };
+
+        let x = parts[1].parse::<char>().ok()?;
+        let y = parts[2].parse::<char>().ok()?;
+        if is_readable(x, mode) == false || (y != 'T' && y != 'J') {
+            return None;
+        }
+
+        Some(Self { op, x, y })
+    }
+}
+
+struct Interpreter {
+    instructions: Vec<Instruction>,
+    mode: Mode,
+}
+
+impl Interpreter {
+    fn from_script(script: &Script) -> Result<Self, ScriptError> {
+        let mode = match script.lines.last().map(|s| s.trim()) {
+            Some("WALK") => Mode::Walk,
+            Some("RUN") => Mode::Run,
+            _ => return Err(ScriptError::MissingMode),
+        };
+
+        let body = &script.lines[..script.lines.len() - 1];
+        if body.len() > MAX_INSTRUCTIONS {
+            return Err(ScriptError::TooLong(body.len()));
+        }
+
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for (i, line) in body.iter().enumerate() {
+            match Instruction::from_string(line, mode) {
+                Some(instruction) => instructions.push(instruction),
+                None => return Err(ScriptError::BadInstruction(i + 1, line.clone())),
+            }
+        }
+
+        Ok(Self { instructions, mode })
+    }
+
+    fn range(&self) -> usize {
+        sensor_idx(self.mode.last_sensor()) + 1
+    }
+
+    // sensors[0] is register A, one tile ahead of the droid
+    fn should_jump(&self, sensors: &[bool]) -> bool {
+        let mut t = false;
+        let mut j = false;
+        for instruction in &self.instructions {
+            let x = match instruction.x {
+                'T' => t,
+                'J' => j,
+                c => sensors[sensor_idx(c)],
+            };
+            let y = if instruction.y == 'T' { &mut t } else { &mut j };
+            *y = match instruction.op {
+                Op::And => x && *y,
+                Op::Or => x || *y,
+                Op::Not => !x,
+            };
+        }
+        j
+    }
+
+    // Walks the droid across the hull, where '#' is ground and '.' is a hole. Returns where the droid fell, if it did.
+    fn walk_hull(&self, hull: &str) -> Option<usize> {
+        let hull: Vec<bool> = hull.trim().chars().map(|c| c == '#').collect();
+        let mut position = 0;
+        while position < hull.len() {
+            let sensors: Vec<bool> = (1..=self.range())
+                .map(|distance| read_hull(&hull, position + distance))
+                .collect();
+
+            position += if self.should_jump(&sensors) == true {
+                4
+            } else {
+                1
+            };
+            if read_hull(&hull, position) == false {
+                return Some(position);
+            }
+        }
+        None
+    }
+}
+
 struct Droid {
     program: Program,
     script: Script,
@@ -365,6 +889,248 @@ impl Droid {
 
         panic!("Program halted without completing");
     }
+
+    // Like run, but quietly. If the droid doesn't make it, the output is returned to show why.
+    fn try_run(&mut self) -> Result<i64, String> {
+        self.give_springscript();
+
+        let mut output = String::new();
+        while self.program.halted == false {
+            self.program.run_with_pause();
+            for i in self.program.output.drain(..) {
+                if i < 128 {
+                    output.push((i as u8) as char);
+                } else {
+                    return Ok(i);
+                }
+            }
+
+            if self.program.input_needed == true {
+                panic!("Input needed!");
+            }
+        }
+
+        Err(output)
+    }
+}
+
+// The droid's failure drawing repeats the hull in every frame, with the droid starting at the left edge
+fn parse_failure(output: &str) -> Option<String> {
+    output
+        .lines()
+        .rev()
+        .find(|line| line.contains('#') && line.chars().all(|c| c == '#' || c == '.'))
+        .map(|line| line.to_owned())
+}
+
+// Only the sensors in mask are read, the rest are left as 0
+fn sensor_pattern(hull: &[bool], position: usize, mask: u16) -> u16 {
+    (0..16)
+        .filter(|i| mask & (1 << i) != 0)
+        .fold(0, |pattern, i| {
+            if read_hull(hull, position + i + 1) == true {
+                pattern | (1 << i)
+            } else {
+                pattern
+            }
+        })
+}
+
+// Decide whether to jump for each sensor pattern seen, such that the droid makes it across every hull. Jumping is
+// tried first, and only given up on where it lands the droid in trouble.
+fn assign_jumps(
+    hulls: &[Vec<bool>],
+    mask: u16,
+    table: &mut HashMap<u16, bool>,
+    hull_idx: usize,
+    position: usize,
+) -> bool {
+    if hull_idx == hulls.len() {
+        return true;
+    }
+
+    let hull = &hulls[hull_idx];
+    if position >= hull.len() {
+        return assign_jumps(hulls, mask, table, hull_idx + 1, 0);
+    }
+
+    let pattern = sensor_pattern(hull, position, mask);
+    let known = table.get(&pattern).copied();
+    let choices = match known {
+        Some(jump) => vec![jump],
+        None => vec![true, false],
+    };
+
+    for jump in choices {
+        let next = position + if jump == true { 4 } else { 1 };
+        if read_hull(hull, next) == false {
+            continue;
+        }
+
+        table.insert(pattern, jump);
+        if assign_jumps(hulls, mask, table, hull_idx, next) == true {
+            return true;
+        }
+        if known.is_none() {
+            table.remove(&pattern);
+        }
+    }
+
+    false
+}
+
+// A product term: each sensor in `care` must read the matching bit of `bits`
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cube {
+    care: u16,
+    bits: u16,
+}
+
+impl Cube {
+    fn contains(&self, minterm: u16) -> bool {
+        minterm & self.care == self.bits
+    }
+
+    fn expr(&self, range: usize) -> Expr {
+        let mut literals: Vec<Expr> = Vec::new();
+        for i in 0..range {
+            if self.care & (1 << i) != 0 {
+                let sensor = Expr::Sensor((b'A' + i as u8) as char);
+                if self.bits & (1 << i) != 0 {
+                    literals.push(sensor);
+                } else {
+                    literals.push(sensor.negate());
+                }
+            }
+        }
+
+        if literals.len() == 1 {
+            literals.remove(0)
+        } else {
+            Expr::And(literals)
+        }
+    }
+}
+
+// Every minterm not in `off` is a don't-care, so the largest cubes that avoid `off` are the prime implicants
+fn prime_cubes(off: &[u16], mask: u16, range: usize) -> Vec<Cube> {
+    let is_valid = |cube: &Cube| off.iter().all(|&m| cube.contains(m) == false);
+
+    let mut primes: Vec<Cube> = Vec::new();
+    for care in (0..(1u16 << range)).filter(|care| care & !mask == 0) {
+        let mut bits = care;
+        loop {
+            let cube = Cube { care, bits };
+            let is_prime = is_valid(&cube) == true
+                && (0..range).filter(|i| care & (1 << i) != 0).all(|i| {
+                    let bigger = Cube {
+                        care: care & !(1 << i),
+                        bits: bits & !(1 << i),
+                    };
+                    is_valid(&bigger) == false
+                });
+            if is_prime == true {
+                primes.push(cube);
+            }
+
+            // Step through every subset of care
+            if bits == 0 {
+                break;
+            }
+            bits = (bits - 1) & care;
+        }
+    }
+    primes
+}
+
+// Greedy cover of the on-set with prime implicants, as a sum of products
+fn sum_of_products(on: &[u16], off: &[u16], mask: u16, range: usize) -> Expr {
+    let primes = prime_cubes(off, mask, range);
+    let mut uncovered: Vec<u16> = on.to_vec();
+    let mut terms: Vec<Expr> = Vec::new();
+    while uncovered.is_empty() == false {
+        let best = primes
+            .iter()
+            .max_by_key(|cube| {
+                let covered = uncovered.iter().filter(|&&m| cube.contains(m)).count();
+                (covered, -(cube.care.count_ones() as i32))
+            })
+            .unwrap();
+        uncovered.retain(|&m| best.contains(m) == false);
+        terms.push(best.expr(range));
+    }
+
+    if terms.len() == 1 {
+        terms.remove(0)
+    } else {
+        Expr::Or(terms)
+    }
+}
+
+fn synthesize(table: &HashMap<u16, bool>, mask: u16, mode: Mode) -> Option<Script> {
+    let range = sensor_idx(mode.last_sensor()) + 1;
+    let on: Vec<u16> = table.iter().filter(|(_, &j)| j).map(|(&p, _)| p).collect();
+    let off: Vec<u16> = table.iter().filter(|(_, &j)| !j).map(|(&p, _)| p).collect();
+
+    // Constant results don't need any sensors at all
+    if on.is_empty() == true {
+        return Some(Script {
+            lines: vec!["AND T J".to_owned(), mode.to_string()],
+        });
+    } else if off.is_empty() == true {
+        return Some(Script {
+            lines: vec!["NOT T J".to_owned(), mode.to_string()],
+        });
+    }
+
+    // Either cover where to jump directly, or cover where not to jump and invert it
+    let direct = Script::from_expr(&sum_of_products(&on, &off, mask, range), mode);
+    let inverted = Script::from_expr(&sum_of_products(&off, &on, mask, range).negate(), mode);
+    match (direct, inverted) {
+        (Ok(a), Ok(b)) if b.lines.len() < a.lines.len() => Some(b),
+        (Ok(a), _) => Some(a),
+        (_, Ok(b)) => Some(b),
+        _ => None,
+    }
+}
+
+// Look for a script using as few sensors as possible. Fewer sensors means more hulls share the same pattern, so what
+// was learned on one hull is more likely to hold on the next.
+fn plan_script(hulls: &[Vec<bool>], mode: Mode) -> Option<Script> {
+    let range = sensor_idx(mode.last_sensor()) + 1;
+    let mut masks: Vec<u16> = (0..(1u16 << range)).collect();
+    masks.sort_by_key(|mask| mask.count_ones());
+
+    for mask in masks {
+        let mut table: HashMap<u16, bool> = HashMap::new();
+        if assign_jumps(hulls, mask, &mut table, 0, 0) == true {
+            if let Some(script) = synthesize(&table, mask, mode) {
+                return Some(script);
+            }
+        }
+    }
+    None
+}
+
+const MAX_ATTEMPTS: usize = 100;
+
+// Keep running scripts on the droid, learning from each hull it falls through, until one makes it across
+fn learn_script(code: &[i64], mode: Mode) -> Option<(Script, i64)> {
+    let mut hulls: Vec<Vec<bool>> = Vec::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let script = plan_script(&hulls, mode)?;
+
+        let mut droid = Droid::new(Program::new(code, &[]));
+        droid.script = script;
+        match droid.try_run() {
+            Ok(damage) => return Some((droid.script, damage)),
+            Err(output) => {
+                let hull = parse_failure(&output)?;
+                hulls.push(hull.chars().map(|c| c == '#').collect());
+            }
+        }
+    }
+    None
 }
 
 #[aoc(day21, part2)]
@@ -374,36 +1140,217 @@ pub fn solve(input: &str) -> i64 {
         .split(',')
         .map(|s| s.parse::<i64>().unwrap())
         .collect();
-    let program = Program::new(&code, &[]);
-    let mut droid = Droid::new(program);
-
-    // Check if I need to jump
-    droid.script.add_line("NOT A J"); // J: ~A
-    droid.script.add_line("NOT B T"); // T: ~B
-    droid.script.add_line("OR T J"); //  J: ~A | ~B
-    droid.script.add_line("NOT C T"); // T: ~C
-    droid.script.add_line("OR T J"); //  J: ~A | ~B | ~C
-
-    // Check if I can jump
-    droid.script.add_line("AND D J"); // J: (~A | ~B | ~C) & D
-
-    // Check if I would need to jump again immediately, and if so can I
-    droid.script.add_line("NOT E T"); // T: ~E
-    droid.script.add_line("NOT T T"); // T: E
-    droid.script.add_line("OR H T"); //  T: E | H
-    droid.script.add_line("AND T J"); // J: (~A | ~B | ~C) & D & (E | H)
-    droid.script.add_line("RUN");
-
-    let damage = droid.run();
+
+    let (script, damage) = match learn_script(&code, Mode::Run) {
+        Some(learned) => learned,
+        None => panic!("Could not learn a springscript that makes it across"),
+    };
+    println!("{}", script.lines.join("\n"));
     println!("Hull damage: {}", damage);
     damage
 }
 
 #[cfg(test)]
 mod test {
-    #[allow(unused_imports)] // No tests for this module
     use super::*;
+    use std::fs;
 
     #[test]
-    fn test_() {}
+    fn test_compile() {
+        let script = Script::compile("(!A | !B | !C) & D & (E | H)", Mode::Run).unwrap();
+        assert_eq!(
+            script.lines,
+            [
+                "OR A J", "AND B J", "AND C J", "NOT J J", "AND D J", "OR E T", "OR H T",
+                "AND T J", "RUN"
+            ]
+        );
+
+        // (E & F) & (G | H) is one gate, so T is free for it once (A | B) & (C | D) is in J
+        let script = Script::compile("(A | B) & (C | D) | (E & F) & (G | H)", Mode::Run).unwrap();
+        assert_eq!(
+            script.lines,
+            [
+                "OR A J", "OR B J", "OR C T", "OR D T", "AND T J", "NOT G T", "NOT T T", "OR H T",
+                "AND E T", "AND F T", "OR T J", "RUN"
+            ]
+        );
+
+        let script = Script::compile("~D", Mode::Walk).unwrap();
+        assert_eq!(script.lines, ["NOT D J", "WALK"]);
+
+        let script = Script::compile("!(A & B) | C", Mode::Walk).unwrap();
+        assert_eq!(
+            script.lines,
+            ["OR A J", "AND B J", "NOT J J", "OR C J", "WALK"]
+        );
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert_eq!(
+            Script::compile("A & E", Mode::Walk).unwrap_err(),
+            CompileError::UnknownSensor(4, 'E')
+        );
+        assert_eq!(
+            Script::compile("A & (B | C", Mode::Walk).unwrap_err(),
+            CompileError::UnexpectedEnd
+        );
+        assert_eq!(
+            Script::compile("A B", Mode::Walk).unwrap_err(),
+            CompileError::UnexpectedChar(2, 'B')
+        );
+
+        // Parity of five sensors needs at least 16 AND/OR gates, so it can never fit
+        let xor = |a: &str, b: &str| format!("({} & !{} | !{} & {})", a, b, a, b);
+        let parity = ["B", "C", "D", "E"]
+            .iter()
+            .fold("A".to_owned(), |expr, sensor| xor(&expr, sensor));
+        assert_eq!(
+            Script::compile(&parity, Mode::Run).unwrap_err(),
+            CompileError::TooComplex
+        );
+        assert_eq!(
+            Script::compile(
+                "(((A & !B) | (!A & B)) & !C | !(D & !E)) & (!F | G & !H) | I",
+                Mode::Run
+            )
+            .unwrap_err(),
+            CompileError::TooLong(18)
+        );
+    }
+
+    fn script_from_str(string: &str) -> Script {
+        Script {
+            lines: string.lines().map(|s| s.trim().to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let interpreter = Interpreter::from_script(&script_from_str("NOT D J\nWALK")).unwrap();
+        assert_eq!(interpreter.walk_hull("#####.###########"), Some(5));
+
+        let three_wide = script_from_str(
+            "NOT A J
+            NOT B T
+            AND T J
+            NOT C T
+            AND T J
+            AND D J
+            WALK",
+        );
+        let interpreter = Interpreter::from_script(&three_wide).unwrap();
+        assert_eq!(interpreter.walk_hull("#####...#########"), None);
+        assert_eq!(interpreter.walk_hull("#####.#..########"), Some(5));
+
+        assert_eq!(
+            Interpreter::from_script(&script_from_str("NOT E J\nWALK")).err(),
+            Some(ScriptError::BadInstruction(1, "NOT E J".to_owned()))
+        );
+        assert_eq!(
+            Interpreter::from_script(&script_from_str("NOT A B\nRUN")).err(),
+            Some(ScriptError::BadInstruction(1, "NOT A B".to_owned()))
+        );
+        assert_eq!(
+            Interpreter::from_script(&script_from_str("NOT A J")).err(),
+            Some(ScriptError::MissingMode)
+        );
+    }
+
+    #[test]
+    fn test_compiled_scripts() {
+        let expressions = [
+            "(!A | !B | !C) & D & (E | H)",
+            "!(A & B) | C",
+            "(A | !B) & !(C & (D | !E)) | F & G & !H",
+            "~A & ~B & ~C & ~D & ~E & ~F & ~G & ~H & ~I",
+            "(A | B) & (C | D) | (E & F) & (G | H)",
+        ];
+        for expression in expressions.iter() {
+            let expr = Parser::parse(expression, Mode::Run).unwrap();
+            let interpreter =
+                Interpreter::from_script(&Script::compile(expression, Mode::Run).unwrap()).unwrap();
+            for bits in 0..(1 << 9) {
+                let sensors: Vec<bool> = (0..9).map(|i| bits & (1 << i) != 0).collect();
+                assert_eq!(interpreter.should_jump(&sensors), expr.eval(&sensors));
+            }
+        }
+
+        let interpreter = Interpreter::from_script(
+            &Script::compile("(!A | !B | !C) & D & (E | H)", Mode::Run).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(interpreter.walk_hull("#####.#.##..#.###"), None);
+        assert_eq!(interpreter.walk_hull("#####...#########"), None);
+        assert_eq!(interpreter.walk_hull("#####.##.#.######"), None);
+    }
+
+    #[test]
+    fn test_parse_failure() {
+        let output = "Input instructions:
+
+Walking...
+
+
+Didn't make it across:
+
+.................
+.................
+@................
+#####.##.########
+
+.................
+.................
+.@...............
+#####.##.########
+";
+        assert_eq!(parse_failure(output), Some("#####.##.########".to_owned()));
+        assert_eq!(parse_failure("Walking..."), None);
+    }
+
+    #[test]
+    fn test_assign_jumps() {
+        let hulls: Vec<Vec<bool>> = ["#####.##.########", "#####..#.########"]
+            .iter()
+            .map(|hull| hull.chars().map(|c| c == '#').collect())
+            .collect();
+        let mut table: HashMap<u16, bool> = HashMap::new();
+        assert!(assign_jumps(&hulls, 0b1111, &mut table, 0, 0));
+
+        let script = synthesize(&table, 0b1111, Mode::Walk).unwrap();
+        let interpreter = Interpreter::from_script(&script).unwrap();
+        assert_eq!(interpreter.walk_hull("#####.##.########"), None);
+        assert_eq!(interpreter.walk_hull("#####..#.########"), None);
+
+        // Holes five wide can't be jumped
+        let hulls = vec!["#####.....#######".chars().map(|c| c == '#').collect()];
+        let mut table: HashMap<u16, bool> = HashMap::new();
+        assert!(assign_jumps(&hulls, 0b1111, &mut table, 0, 0) == false);
+    }
+
+    #[test]
+    fn test_learn_script() {
+        let input = fs::read_to_string("input/2019/day21.txt")
+            .expect("Something went wrong reading the file");
+        let code: Vec<i64> = input
+            .trim()
+            .split(',')
+            .map(|s| s.parse::<i64>().unwrap())
+            .collect();
+
+        let expected = [(Mode::Walk, 19352864), (Mode::Run, 1142488337)];
+        for &(mode, expected_damage) in expected.iter() {
+            let (script, damage) = learn_script(&code, mode).unwrap();
+            assert_eq!(damage, expected_damage);
+            assert_eq!(script.lines.last(), Some(&mode.to_string()));
+            assert!(script.lines.len() <= MAX_INSTRUCTIONS + 1);
+        }
+
+        // The hand-derived expression gets the same result: jump if there's a hole coming up and it's
+        // possible to land, then check that another jump right after landing is possible if needed.
+        let mut droid = Droid::new(Program::new(&code, &[]));
+        droid.script = Script::compile("(!A | !B | !C) & D & (E | H)", Mode::Run).unwrap();
+        assert_eq!(droid.run(), 1142488337);
+    }
 }
@@ -51,16 +51,25 @@ fn modulo_mult(x: i64, y: i64, modulus: i64) -> i64 {
     result as i64
 }
 
-fn modulo_div(x: i64, n: i64, modulus: i64) -> i64 {
-    // x * y = n mod m
-    // Given x and n, find the inverse of x then multiply by n to get y.
-    // E.g. 7 * 5 = 35 = 16 mod 19, given x = 7 and n = 16 find inv_x = -8 then y = -8 * 16 mod 19
-    let inv_x = gcd_extended(x, modulus);
-    modulo_mult(inv_x, n, modulus)
+// x * y + z, done in i128 so the sum can't overflow even when the modulus is close to i64::MAX
+fn modulo_mult_add(x: i64, y: i64, z: i64, modulus: i64) -> i64 {
+    let result = modulo_i128((x as i128) * (y as i128) + (z as i128), modulus as i128);
+    result as i64
+}
+
+fn modulo_inverse(x: i64, modulus: i64) -> Option<i64> {
+    // x * inv_x = 1 mod m, which only exists when x and m share no factors.
+    // E.g. 7 * -8 = -56 = 1 mod 19, so inv_x = 11
+    let (gcd, inv_x) = gcd_extended(modulo(x, modulus), modulus);
+    if gcd == 1 {
+        Some(modulo(inv_x, modulus))
+    } else {
+        None
+    }
 }
 
 #[allow(clippy::many_single_char_names)]
-fn gcd_extended(a: i64, b: i64) -> i64 {
+fn gcd_extended(a: i64, b: i64) -> (i64, i64) {
     let mut s = 0;
     let mut old_s = 1;
     let mut t = 1;
@@ -88,7 +97,7 @@ fn gcd_extended(a: i64, b: i64) -> i64 {
     println!("greatest common divisor: {}", old_r);
     println!("quotients by the gcd: {}, {}", t, s);*/
 
-    old_s
+    (old_r, old_s)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -120,51 +129,6 @@ impl Technique {
 
         panic!("Unknown technique: {}", s);
     }
-
-    fn combine_or_swap(x: Self, y: Self, modulus: i64) -> Vec<Self> {
-        match x {
-            Self::DealNewStack => match y {
-                Self::DealNewStack => vec![],
-                Self::DealWithIncrement(y_val) => {
-                    let n = modulo(-y_val, modulus);
-                    vec![Self::DealWithIncrement(n), Self::Cut(y_val)]
-                }
-                Self::Cut(y_val) => {
-                    let n = modulo(-y_val, modulus);
-                    vec![Self::Cut(n), Self::DealNewStack]
-                }
-            },
-            Self::DealWithIncrement(x_val) => {
-                match y {
-                    Self::DealNewStack => {
-                        let n = modulo(-x_val, modulus);
-                        vec![Self::DealWithIncrement(n), Self::Cut(1)]
-                    }
-                    Self::DealWithIncrement(y_val) => {
-                        let n = modulo_mult(x_val, y_val, modulus);
-                        vec![Self::DealWithIncrement(n)]
-                    }
-                    Self::Cut(_y_val) => {
-                        unimplemented!() // Didn't figure out a good solution for this, but it's not needed since we always pull DealWithIncrement in front of Cut
-                    }
-                }
-            }
-            Self::Cut(x_val) => match y {
-                Self::DealNewStack => {
-                    let n = modulo(-x_val, modulus);
-                    vec![Self::DealNewStack, Self::Cut(n)]
-                }
-                Self::DealWithIncrement(y_val) => {
-                    let n = modulo_mult(x_val, y_val, modulus);
-                    vec![Self::DealWithIncrement(y_val), Self::Cut(n)]
-                }
-                Self::Cut(y_val) => {
-                    let n = modulo(x_val + y_val, modulus);
-                    vec![Self::Cut(n)]
-                }
-            },
-        }
-    }
 }
 
 impl fmt::Display for Technique {
@@ -226,81 +190,138 @@ impl Deck {
     }
 }
 
-fn reduce(techniques: &[Technique], modulus: i64) -> Vec<Technique> {
-    // Combine repeated techniques. Pull DealWithIncrement to the front. Push Cut to the back.
-    // Thanks to /u/MegaGreenLightning for the idea
-    let mut current_vec = techniques.to_owned();
-    let mut fresh_data = true;
-    while fresh_data == true {
-        fresh_data = false;
-        let mut next_vec = Vec::new();
-        let mut i = 0;
-        while (i + 1) < current_vec.len() {
-            // First 3 match lines are for combining repeated techniques.
-            // Next line is for moving DealWithIncrement to the front.
-            // Last line is for moving Cut to the back.
-            match (current_vec[i], current_vec[i + 1]) {
-                (Technique::DealNewStack, Technique::DealNewStack)
-                | (Technique::DealWithIncrement(_), Technique::DealWithIncrement(_))
-                | (Technique::Cut(_), Technique::Cut(_))
-                | (_, Technique::DealWithIncrement(_))
-                | (Technique::Cut(_), _) => {
-                    let result =
-                        Technique::combine_or_swap(current_vec[i], current_vec[i + 1], modulus);
-                    next_vec.extend(result);
-                    i += 2; // Consume both items
-                    fresh_data = true; // Set flag so that this pass is executed again (some data in the vec changed)
-                }
+#[derive(Debug, PartialEq)]
+enum ShuffleError {
+    EmptyDeck,
+    // Dealing with an increment that shares a factor with the deck size stacks cards on the same position
+    SharedFactor {
+        index: usize,
+        technique: Technique,
+        size: i64,
+    },
+}
 
-                _ => {
-                    // Nothing to do
-                    next_vec.push(current_vec[i]);
-                    i += 1; // Consume this item
-                }
-            }
+impl fmt::Display for ShuffleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShuffleError::EmptyDeck => write!(f, "Deck has no cards"),
+            ShuffleError::SharedFactor {
+                index,
+                technique,
+                size,
+            } => write!(
+                f,
+                "{} (step {}) shares a factor with deck size {}",
+                technique, index, size
+            ),
+        }
+    }
+}
+
+// Every technique moves the card at position x to a*x + b (mod deck size), and so does any
+// sequence of them, so a whole shuffle process boils down to just the two numbers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Shuffle {
+    a: i64,
+    b: i64,
+    size: i64,
+}
+
+impl Shuffle {
+    fn identity(size: i64) -> Self {
+        Self {
+            a: modulo(1, size),
+            b: 0,
+            size,
         }
+    }
 
-        // Last item in list may not have been processed. Do it now.
-        if i < current_vec.len() {
-            next_vec.push(current_vec[i]);
+    fn from_technique(technique: Technique, size: i64) -> Self {
+        let (a, b) = match technique {
+            Technique::DealNewStack => (-1, -1),
+            Technique::DealWithIncrement(n) => (n, 0),
+            Technique::Cut(n) => (1, -n),
+        };
+        Self {
+            a: modulo(a, size),
+            b: modulo(b, size),
+            size,
         }
+    }
 
-        current_vec = next_vec;
+    fn from_techniques(techniques: &[Technique], size: i64) -> Self {
+        techniques.iter().fold(Self::identity(size), |shuffle, &t| {
+            shuffle.then(&Self::from_technique(t, size))
+        })
     }
 
-    current_vec
-}
+    // Same as from_techniques but refuses anything that can't be done with a real deck
+    fn checked(techniques: &[Technique], size: i64) -> Result<Self, ShuffleError> {
+        if size <= 0 {
+            return Err(ShuffleError::EmptyDeck);
+        }
+        for (index, &technique) in techniques.iter().enumerate() {
+            if let Technique::DealWithIncrement(n) = technique {
+                if gcd_extended(modulo(n, size), size).0 != 1 {
+                    return Err(ShuffleError::SharedFactor {
+                        index,
+                        technique,
+                        size,
+                    });
+                }
+            }
+        }
+        Ok(Self::from_techniques(techniques, size))
+    }
 
-fn expand(techniques: &[Technique], modulus: i64, target: i64) -> Vec<Technique> {
-    // For each of the 64 bits, create a techniques vector that can be performed to accomplish that number of shuffles. Not all bits will be needed.
-    let mut bit_vec: Vec<Vec<Technique>> = Vec::new();
-    let mut techniques_multiple = techniques.to_owned();
-    for _ in 0..64 {
-        bit_vec.push(techniques_multiple.clone());
-        techniques_multiple.extend(techniques_multiple.clone()); // Double the vector
-        techniques_multiple = reduce(&techniques_multiple, modulus);
+    // This shuffle followed by the other: a2 * (a1 * x + b1) + b2
+    fn then(&self, other: &Self) -> Self {
+        assert_eq!(self.size, other.size, "Shuffles are for different decks");
+        Self {
+            a: modulo_mult(other.a, self.a, self.size),
+            b: modulo_mult_add(other.a, self.b, other.b, self.size),
+            size: self.size,
+        }
     }
 
-    let mut target_vec: Vec<Technique> = Vec::new();
-    for (i, bit) in bit_vec.iter().enumerate().take(64) {
-        if (target & (1 << i)) != 0 {
-            target_vec.extend(bit);
+    fn repeat(&self, count: u64) -> Self {
+        // Exponentiation by squaring
+        let mut result = Self::identity(self.size);
+        let mut square = *self;
+        let mut count = count;
+        while count > 0 {
+            if count & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            count >>= 1;
         }
+        result
+    }
+
+    // Only exists when a shares no factors with the deck size, otherwise some cards were dealt
+    // onto the same position and the shuffle can't be undone.
+    fn inverse(&self) -> Option<Self> {
+        let inv_a = modulo_inverse(self.a, self.size)?;
+        Some(Self {
+            a: inv_a,
+            b: modulo_mult(inv_a, modulo(-self.b, self.size), self.size),
+            size: self.size,
+        })
+    }
+
+    fn position_of(&self, card: i64) -> i64 {
+        modulo_mult_add(self.a, card, self.b, self.size)
     }
 
-    target_vec = reduce(&target_vec, modulus);
-    target_vec
+    fn card_at(&self, position: i64) -> Option<i64> {
+        self.inverse().map(|inverse| inverse.position_of(position))
+    }
 }
 
-fn get_card_at_position(techniques: &[Technique], modulus: i64, position: usize) -> i64 {
-    match techniques[..] {
-        [Technique::DealWithIncrement(x), Technique::Cut(c)] => {
-            // DealWithIncrement is the same as modular multiplication. We are trying to undo it and so
-            // use modular division. Note that we are trying to find the card that ends up in a position, but
-            // since there is a Cut after the Deal we need to adjust the position we are actually looking for.
-            modulo_div(x, position as i64 + c, modulus)
-        }
-        _ => panic!("Reduced vec not in expected form"), // Expect to be in the form: DealWithIncrement, Cut
+impl fmt::Display for Shuffle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "x -> {}x + {} mod {}", self.a, self.b, self.size)
     }
 }
 
@@ -313,9 +334,14 @@ pub fn solve(input: &str) -> i64 {
 
     let deck_size = 119315717514047;
     let shuffle_count = 101741582076661;
-    let reduce_result = reduce(&techniques, deck_size);
-    let expand_result = expand(&reduce_result, deck_size, shuffle_count);
-    let card_num = get_card_at_position(&expand_result, deck_size, 2020);
+    let shuffle = match Shuffle::checked(&techniques, deck_size) {
+        Ok(shuffle) => shuffle.repeat(shuffle_count),
+        Err(e) => panic!("{}", e),
+    };
+    let card_num = match shuffle.card_at(2020) {
+        Some(card) => card,
+        None => panic!("Shuffle {} can't be undone", shuffle),
+    };
     println!("Card {} ended up in position 2020", card_num);
     card_num
 }
@@ -342,6 +368,9 @@ mod test {
             119_315_717_514_047,
         );
         assert_eq!(result, 32154407593923);
+
+        let result = modulo_mult_add(i64::MAX - 1, 1, i64::MAX - 1, i64::MAX);
+        assert_eq!(result, i64::MAX - 2);
     }
 
     #[test]
@@ -417,165 +446,66 @@ mod test {
         assert_eq!(deck.cards, [9, 2, 5, 8, 1, 4, 7, 0, 3, 6]);
     }
 
-    fn both_ways_combine(t1: Technique, t2: Technique, deck_size: i64) -> (Deck, Deck) {
+    #[test]
+    fn test_modulo_inverse() {
+        assert_eq!(modulo_inverse(7, 19), Some(11));
+        assert_eq!(modulo_inverse(-3, 10), Some(3));
+        assert_eq!(modulo_inverse(4, 10), None);
+        assert_eq!(modulo_inverse(0, 10007), None);
+
+        let inv = modulo_inverse(0x0FFF_FFFF, 119_315_717_514_047).unwrap();
+        assert_eq!(modulo_mult(inv, 0x0FFF_FFFF, 119_315_717_514_047), 1);
+    }
+
+    fn both_ways(techniques: &[Technique], deck_size: i64) -> (Deck, Deck) {
         let mut deck1 = Deck::new(deck_size as usize);
-        deck1.shuffle(t1);
-        deck1.shuffle(t2);
+        deck1.shuffle_many(techniques);
 
-        let techniques_combined = Technique::combine_or_swap(t1, t2, deck_size);
+        // Place each card where the shuffle says it goes
+        let shuffle = Shuffle::from_techniques(techniques, deck_size);
         let mut deck2 = Deck::new(deck_size as usize);
-        deck2.shuffle_many(&techniques_combined);
+        for card in 0..deck_size {
+            deck2.cards[shuffle.position_of(card) as usize] = card as usize;
+        }
 
         (deck1, deck2)
     }
 
     #[test]
-    fn test_combine_or_swap() {
+    fn test_shuffle() {
         for &deck_size in [3, 5, 7, 11, 13, 17, 19, 23, 29, 31].iter() {
             // Prime deck sizes mean every x and y value less than deck_size is valid
-            // DealNewStack + DealNewStack
-            let (deck1, deck2) =
-                both_ways_combine(Technique::DealNewStack, Technique::DealNewStack, deck_size);
-            assert_eq!(deck1, deck2);
-
-            // DealNewStack + DealWithIncrement
+            let mut techniques = vec![Technique::DealNewStack];
             for x in 1..deck_size {
-                let (deck1, deck2) = both_ways_combine(
-                    Technique::DealNewStack,
-                    Technique::DealWithIncrement(x),
-                    deck_size,
-                );
-                assert_eq!(deck1, deck2);
-            }
-
-            // DealNewStack + Cut
-            for x in 1..deck_size {
-                let (deck1, deck2) =
-                    both_ways_combine(Technique::DealNewStack, Technique::Cut(x), deck_size);
-                assert_eq!(deck1, deck2);
-            }
-
-            // DealWithIncrement + DealNewStack
-            for x in 1..deck_size {
-                let (deck1, deck2) = both_ways_combine(
-                    Technique::DealWithIncrement(x),
-                    Technique::DealNewStack,
-                    deck_size,
-                );
-                assert_eq!(deck1, deck2);
-            }
-
-            // DealWithIncrement + DealWithIncrement
-            for x in 1..deck_size {
-                for y in 1..deck_size {
-                    let (deck1, deck2) = both_ways_combine(
-                        Technique::DealWithIncrement(x),
-                        Technique::DealWithIncrement(y),
-                        deck_size,
-                    );
-                    assert_eq!(deck1, deck2);
-                }
-            }
-
-            // DealWithIncrement + Cut
-            // Not implemented
-            /*for x in 1..deck_size {
-                for y in 1..deck_size {
-                    let (deck1, deck2) = both_ways_combine(Technique::DealWithIncrement(x), Technique::Cut(y), deck_size);
-                    assert_eq!(deck1, deck2);
-                }
-            }*/
-
-            // Cut + DealNewStack
-            for x in 1..deck_size {
-                let (deck1, deck2) =
-                    both_ways_combine(Technique::Cut(x), Technique::DealNewStack, deck_size);
-                assert_eq!(deck1, deck2);
-            }
-
-            // Cut + DealWithIncrement
-            for x in 1..deck_size {
-                for y in 1..deck_size {
-                    let (deck1, deck2) = both_ways_combine(
-                        Technique::Cut(x),
-                        Technique::DealWithIncrement(y),
-                        deck_size,
-                    );
-                    assert_eq!(deck1, deck2);
-                }
+                techniques.push(Technique::DealWithIncrement(x));
+                techniques.push(Technique::Cut(x));
+                techniques.push(Technique::Cut(-x));
             }
 
-            // Cut + Cut
-            for x in 1..deck_size {
-                for y in 1..deck_size {
-                    let (deck1, deck2) =
-                        both_ways_combine(Technique::Cut(x), Technique::Cut(y), deck_size);
-                    assert_eq!(deck1, deck2);
+            for &t1 in techniques.iter() {
+                for &t2 in techniques.iter() {
+                    let (deck1, deck2) = both_ways(&[t1, t2], deck_size);
+                    assert_eq!(deck1, deck2, "{} then {}", t1, t2);
                 }
             }
         }
-    }
-
-    fn both_ways_reduce(techniques: &Vec<Technique>, deck_size: i64) -> (Deck, Deck) {
-        let mut deck1 = Deck::new(deck_size as usize);
-        deck1.shuffle_many(&techniques);
-
-        let mut deck2 = Deck::new(deck_size as usize);
-        let result = reduce(&techniques, deck_size);
-        deck2.shuffle_many(&result);
-
-        (deck1, deck2)
-    }
-
-    #[test]
-    fn test_reduce() {
-        // Check DealNewStack
-        let techniques = vec![
-            Technique::DealNewStack,
-            Technique::DealNewStack,
-            Technique::DealNewStack,
-        ];
-        let (deck1, deck2) = both_ways_reduce(&techniques, 10007);
-        assert_eq!(deck1, deck2);
-
-        // Check DealWithIncrement
-        let techniques = vec![
-            Technique::DealWithIncrement(1),
-            Technique::DealWithIncrement(2),
-            Technique::DealWithIncrement(3),
-            Technique::DealWithIncrement(4),
-        ];
-        let (deck1, deck2) = both_ways_reduce(&techniques, 10007);
-        assert_eq!(deck1, deck2);
-
-        // Check Cut
-        let techniques = vec![
-            Technique::Cut(1000),
-            Technique::Cut(2000),
-            Technique::Cut(3000),
-            Technique::Cut(4000),
-        ];
-        let (deck1, deck2) = both_ways_reduce(&techniques, 10007);
-        assert_eq!(deck1, deck2);
 
-        // Check multiple
         let techniques = vec![
-            Technique::Cut(1000),
-            Technique::DealNewStack,
             Technique::DealNewStack,
-            Technique::Cut(2000),
-            Technique::Cut(3000),
-            Technique::Cut(4000),
-            Technique::DealWithIncrement(1),
-            Technique::DealWithIncrement(2),
+            Technique::Cut(-2),
+            Technique::DealWithIncrement(7),
+            Technique::Cut(8),
+            Technique::Cut(-4),
+            Technique::DealWithIncrement(7),
+            Technique::Cut(3),
+            Technique::DealWithIncrement(9),
             Technique::DealWithIncrement(3),
-            Technique::DealWithIncrement(4),
-            Technique::DealNewStack,
+            Technique::Cut(-1),
         ];
-        let (deck1, deck2) = both_ways_reduce(&techniques, 10007);
+        let (deck1, deck2) = both_ways(&techniques, 10);
         assert_eq!(deck1, deck2);
 
-        // Check that puzzle input reduces correctly
+        // Check the puzzle input against part 1
         let input = fs::read_to_string("input/2019/day22.txt")
             .expect("Something went wrong reading the file");
 
@@ -584,19 +514,18 @@ mod test {
             .map(|line| Technique::from_string(line.trim()))
             .collect::<Vec<Technique>>();
 
-        let mut deck1 = Deck::new(10007);
-        deck1.shuffle_many(&techniques);
-
-        let mut deck2 = Deck::new(10007);
-        let result = reduce(&techniques, 10007);
-        deck2.shuffle_many(&result);
-
+        let (deck1, deck2) = both_ways(&techniques, 10007);
         assert_eq!(deck1, deck2);
+        let shuffle = Shuffle::from_techniques(&techniques, 10007);
+        assert_eq!(
+            Some(shuffle.position_of(2019) as usize),
+            deck1.find_card(2019)
+        );
     }
 
     #[test]
-    fn test_expand() {
-        // Check that a single vector expands correctly (vec_a expanded by x should match vec_a repeated x times and then reduced)
+    fn test_repeat() {
+        // Check that a shuffle repeated x times matches its techniques repeated x times
         for &modulus in [
             10007,
             999983,
@@ -606,34 +535,236 @@ mod test {
         ]
         .iter()
         {
-            let vec_a = vec![
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
-            ];
-            let result_a = expand(&vec_a, modulus, 6);
-            let vec_b = vec![
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
-                Technique::Cut(1000),
-                Technique::DealNewStack,
-                Technique::DealWithIncrement(5),
+            let techniques = vec![
                 Technique::Cut(1000),
                 Technique::DealNewStack,
                 Technique::DealWithIncrement(5),
             ];
-            let result_b = reduce(&vec_b, modulus);
-            assert_eq!(result_a, result_b);
+            let shuffle = Shuffle::from_techniques(&techniques, modulus);
+            assert_eq!(shuffle.repeat(0), Shuffle::identity(modulus));
+            assert_eq!(shuffle.repeat(1), shuffle);
+            for count in 2..20 {
+                let repeated = techniques.repeat(count);
+                assert_eq!(
+                    shuffle.repeat(count as u64),
+                    Shuffle::from_techniques(&repeated, modulus)
+                );
+            }
+        }
+
+        let mut deck = Deck::new(10);
+        let techniques = vec![Technique::Cut(3), Technique::DealWithIncrement(7)];
+        for _ in 0..13 {
+            deck.shuffle_many(&techniques);
+        }
+        let shuffle = Shuffle::from_techniques(&techniques, 10).repeat(13);
+        for card in 0..10 {
+            assert_eq!(
+                Some(shuffle.position_of(card) as usize),
+                deck.find_card(card as usize)
+            );
+        }
+    }
+
+    #[test]
+    fn test_inverse() {
+        let techniques = vec![
+            Technique::DealWithIncrement(7),
+            Technique::Cut(-2),
+            Technique::DealNewStack,
+        ];
+        let shuffle = Shuffle::from_techniques(&techniques, 10).repeat(5);
+        let inverse = shuffle.inverse().unwrap();
+        assert_eq!(shuffle.then(&inverse), Shuffle::identity(10));
+        assert_eq!(inverse.then(&shuffle), Shuffle::identity(10));
+        for card in 0..10 {
+            assert_eq!(shuffle.card_at(shuffle.position_of(card)), Some(card));
+        }
+
+        // Dealing a deck of 10 with increment 4 puts several cards in the same spot
+        let shuffle = Shuffle::from_technique(Technique::DealWithIncrement(4), 10);
+        assert_eq!(shuffle.inverse(), None);
+        assert_eq!(shuffle.card_at(0), None);
+
+        let shuffle = Shuffle::from_technique(Technique::Cut(3), 119315717514047)
+            .then(&Shuffle::from_technique(
+                Technique::DealWithIncrement(0x0FFF_FFFF),
+                119315717514047,
+            ))
+            .repeat(101741582076661);
+        assert_eq!(shuffle.card_at(shuffle.position_of(2020)), Some(2020));
+
+        // a * x + b would overflow an i64 before it's reduced
+        let size = i64::MAX;
+        let shuffle = Shuffle::from_technique(Technique::DealNewStack, size)
+            .then(&Shuffle::from_technique(Technique::Cut(1), size));
+        assert_eq!(shuffle.position_of(0), size - 2);
+        assert_eq!(shuffle.card_at(size - 2), Some(0));
+    }
+
+    #[test]
+    fn test_checked() {
+        let techniques = vec![
+            Technique::Cut(3),
+            Technique::DealWithIncrement(7),
+            Technique::DealNewStack,
+        ];
+        assert_eq!(
+            Shuffle::checked(&techniques, 10),
+            Ok(Shuffle::from_techniques(&techniques, 10))
+        );
+        assert_eq!(
+            Shuffle::checked(&techniques, 14),
+            Err(ShuffleError::SharedFactor {
+                index: 1,
+                technique: Technique::DealWithIncrement(7),
+                size: 14,
+            })
+        );
+        assert_eq!(
+            Shuffle::checked(&[Technique::DealWithIncrement(-4)], 10)
+                .unwrap_err()
+                .to_string(),
+            "Deal with increment -4 (step 0) shares a factor with deck size 10"
+        );
+        assert_eq!(
+            Shuffle::checked(&techniques, 0),
+            Err(ShuffleError::EmptyDeck)
+        );
+        assert!(Shuffle::checked(&techniques, 1).is_ok());
+    }
+
+    // Small xorshift generator so the property tests are repeatable without extra dependencies
+    struct Random {
+        state: u64,
+    }
+
+    impl Random {
+        fn new(seed: u64) -> Self {
+            Self {
+                state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            }
+        }
+
+        fn next(&mut self) -> u64 {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 7;
+            self.state ^= self.state << 17;
+            self.state
+        }
+
+        fn below(&mut self, n: i64) -> i64 {
+            (self.next() % n as u64) as i64
+        }
+
+        fn technique(&mut self, size: i64) -> Technique {
+            match self.below(3) {
+                0 => Technique::DealNewStack,
+                1 => Technique::DealWithIncrement(self.below(size * 2 + 1) - size),
+                _ => Technique::Cut(self.below(size * 2 + 1) - size),
+            }
+        }
+    }
+
+    // Deal a deck by hand and see whether any two cards land on the same position
+    fn deals_cleanly(increment: i64, size: i64) -> bool {
+        let mut seen = vec![false; size as usize];
+        for card in 0..size {
+            let position = modulo(card * increment, size) as usize;
+            if seen[position] == true {
+                return false;
+            }
+            seen[position] = true;
+        }
+        true
+    }
+
+    // Compare the algebra against the literal deck for one shuffle process, returning a
+    // description of whatever doesn't line up.
+    fn check_case(techniques: &[Technique], size: i64) -> Result<(), String> {
+        let clean = techniques.iter().all(|&t| match t {
+            Technique::DealWithIncrement(n) => deals_cleanly(n, size),
+            _ => true,
+        });
+        let shuffle = match Shuffle::checked(techniques, size) {
+            Ok(_) if clean == false => {
+                return Err("accepted a shuffle that stacks cards".to_string());
+            }
+            Ok(shuffle) => shuffle,
+            Err(e) if clean == true => return Err(format!("rejected a valid shuffle: {}", e)),
+            Err(_) => return Ok(()),
+        };
+
+        let mut deck = Deck::new(size as usize);
+        deck.shuffle_many(techniques);
+        for card in 0..size {
+            let position = shuffle.position_of(card);
+            if deck.cards[position as usize] != card as usize {
+                return Err(format!("card {} placed at {}", card, position));
+            }
+            if shuffle.card_at(position) != Some(card) {
+                return Err(format!("card_at({}) isn't {}", position, card));
+            }
+        }
+
+        let inverse = shuffle.inverse().ok_or("no inverse")?;
+        if shuffle.then(&inverse) != Shuffle::identity(size) {
+            return Err(format!("{} doesn't undo {}", inverse, shuffle));
+        }
+
+        let mut repeated = Deck::new(size as usize);
+        for _ in 0..3 {
+            repeated.shuffle_many(techniques);
         }
+        let shuffle = shuffle.repeat(3);
+        for card in 0..size {
+            if repeated.cards[shuffle.position_of(card) as usize] != card as usize {
+                return Err(format!("card {} misplaced after 3 repeats", card));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_properties() {
+        let mut failures = Vec::new();
+        let mut invalid: Vec<(i64, i64)> = Vec::new();
+        for seed in 0..500 {
+            let mut random = Random::new(seed);
+            let size = 1 + random.below(60);
+            let length = random.below(12) as usize;
+            let techniques: Vec<Technique> = (0..length).map(|_| random.technique(size)).collect();
+
+            if let Err(ShuffleError::SharedFactor {
+                technique: Technique::DealWithIncrement(n),
+                ..
+            }) = Shuffle::checked(&techniques, size)
+            {
+                invalid.push((size, n));
+            }
+            if let Err(e) = check_case(&techniques, size) {
+                let list: Vec<String> = techniques.iter().map(|t| t.to_string()).collect();
+                failures.push(format!(
+                    "seed {}, deck size {}, [{}]: {}",
+                    seed,
+                    size,
+                    list.join(", "),
+                    e
+                ));
+            }
+        }
+
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+
+        // Prime decks only refuse multiples of their size, composite ones should have turned up others
+        let primes = [
+            2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59,
+        ];
+        assert!(invalid
+            .iter()
+            .filter(|(size, _)| primes.contains(size))
+            .all(|(size, n)| n % size == 0));
+        assert!(invalid.iter().any(|(size, n)| n % size != 0));
     }
 }
@@ -58,121 +58,423 @@
     What is the biodiversity rating for the first layout that appears twice?
 */
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-#[derive(Debug, Eq, PartialEq)]
-struct BugSim {
-    state: i32,
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Cell {
+    // Always 0 for Flat and Torus. It's kept so the automaton matches part 2's engine, where levels are recursive.
+    depth: i32,
+    row: i32,
+    col: i32,
 }
 
-impl BugSim {
-    fn from_string(input: &str) -> Self {
-        let mut state: i32 = 0;
-        let mut idx = 0;
-        for c in input.chars() {
-            if c == '.' {
-                // Empty space. Do nothing as the value is already set to 0.
-                idx += 1;
-            } else if c == '#' {
-                // Bug. Set to 1.
-                state |= 1 << idx;
-                idx += 1;
-            } else if c == '\r' || c == '\n' {
-                // Line break, just consume
-            } else {
-                panic!("Unknown input: 0x{:02x}", c as u8);
-            }
+// Decides which tiles exist and which tiles count as adjacent to each other
+trait Topology {
+    fn with_size(width: i32, height: i32) -> Self
+    where
+        Self: Sized;
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn contains(&self, row: i32, col: i32) -> bool {
+        (0..self.height()).contains(&row) && (0..self.width()).contains(&col)
+    }
+    fn neighbours(&self, cell: Cell) -> Vec<Cell>;
+}
+
+// Single level, anything beyond the edge is empty
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Flat {
+    width: i32,
+    height: i32,
+}
+
+impl Topology for Flat {
+    fn with_size(width: i32, height: i32) -> Self {
+        Self { width, height }
+    }
+
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn neighbours(&self, cell: Cell) -> Vec<Cell> {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .map(|(dr, dc)| Cell {
+                depth: cell.depth,
+                row: cell.row + dr,
+                col: cell.col + dc,
+            })
+            .filter(|c| self.contains(c.row, c.col))
+            .collect()
+    }
+}
+
+// Single level, leaving one edge comes back in on the opposite edge
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Torus {
+    width: i32,
+    height: i32,
+}
+
+impl Topology for Torus {
+    fn with_size(width: i32, height: i32) -> Self {
+        Self { width, height }
+    }
+
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn neighbours(&self, cell: Cell) -> Vec<Cell> {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .map(|(dr, dc)| Cell {
+                depth: cell.depth,
+                row: (cell.row + dr).rem_euclid(self.height),
+                col: (cell.col + dc).rem_euclid(self.width),
+            })
+            .collect()
+    }
+}
+
+// How many adjacent bugs make an empty tile infested, and how many let a bug survive
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rules {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
+
+impl Rules {
+    fn new(birth: &[usize], survival: &[usize]) -> Self {
+        Self {
+            birth: birth.to_vec(),
+            survival: survival.to_vec(),
         }
+    }
 
-        Self { state }
+    fn bugs() -> Self {
+        Self::new(&[1, 2], &[1])
     }
 
-    fn get_tile(&self, row: i32, col: i32) -> bool {
-        if (0..5).contains(&row) == false || (0..5).contains(&col) == false {
-            return false; // Invalid tiles may result from looking beyond the grid boundaries, these should always count as empty
+    fn next(&self, alive: bool, adjacent: usize) -> bool {
+        if alive == true {
+            self.survival.contains(&adjacent)
+        } else {
+            self.birth.contains(&adjacent)
         }
-        let idx = row * 5 + col;
-        (self.state & (1 << idx)) != 0
     }
+}
 
-    fn display(&self) {
-        for row in 0..5 {
-            for col in 0..5 {
-                if self.get_tile(row, col) == false {
-                    print!(".");
-                } else {
-                    print!("#");
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Cycle {
+    start: usize,
+    length: usize,
+}
+
+// Levels with no bugs are left out, so two automatons with the same bugs compare equal
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Automaton<T: Topology> {
+    topology: T,
+    rules: Rules,
+    levels: BTreeMap<i32, Vec<bool>>,
+}
+
+impl<T: Topology> Automaton<T> {
+    fn new(topology: T, rules: Rules) -> Self {
+        Self {
+            topology,
+            rules,
+            levels: BTreeMap::new(),
+        }
+    }
+
+    // Level 0 from a scan. The grid size comes from the scan and the rules default to the bugs' rules.
+    fn from_string(input: &str) -> Self {
+        let lines: Vec<&str> = input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| line.is_empty() == false)
+            .collect();
+        let height = lines.len() as i32;
+        let width = lines.first().map_or(0, |line| line.chars().count()) as i32;
+
+        let mut sim = Self::new(T::with_size(width, height), Rules::bugs());
+        for (row, line) in lines.iter().enumerate() {
+            assert_eq!(line.chars().count() as i32, width, "Ragged line: {}", line);
+            for (col, c) in line.chars().enumerate() {
+                let cell = Cell {
+                    depth: 0,
+                    row: row as i32,
+                    col: col as i32,
+                };
+                match c {
+                    '.' | '?' => (),
+                    '#' => sim.set(cell, true),
+                    _ => panic!("Unknown input: 0x{:02x}", c as u32),
                 }
             }
-            println!();
         }
-        println!();
+        sim
     }
 
-    fn count_adjacent(&self, row: i32, col: i32) -> i32 {
-        let mut count = 0;
-        if self.get_tile(row - 1, col) == true {
-            count += 1;
+    fn with_rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn index(&self, cell: Cell) -> usize {
+        (cell.row * self.topology.width() + cell.col) as usize
+    }
+
+    fn get(&self, cell: Cell) -> bool {
+        if self.topology.contains(cell.row, cell.col) == false {
+            return false;
         }
-        if self.get_tile(row + 1, col) == true {
-            count += 1;
+        match self.levels.get(&cell.depth) {
+            Some(level) => level[self.index(cell)],
+            None => false, // If depth isn't in map, then it hasn't been touched before and is empty
         }
-        if self.get_tile(row, col - 1) == true {
-            count += 1;
+    }
+
+    fn set(&mut self, cell: Cell, bug: bool) {
+        if self.topology.contains(cell.row, cell.col) == false {
+            return;
         }
-        if self.get_tile(row, col + 1) == true {
-            count += 1;
+        let idx = self.index(cell);
+        let size = (self.topology.width() * self.topology.height()) as usize;
+        let level = self
+            .levels
+            .entry(cell.depth)
+            .or_insert_with(|| vec![false; size]);
+        level[idx] = bug;
+        if level.contains(&true) == false {
+            self.levels.remove(&cell.depth);
         }
-        count
+    }
+
+    fn cells(&self, depth: i32) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for row in 0..self.topology.height() {
+            for col in 0..self.topology.width() {
+                if self.topology.contains(row, col) == true {
+                    cells.push(Cell { depth, row, col });
+                }
+            }
+        }
+        cells
+    }
+
+    fn count_adjacent(&self, cell: Cell) -> usize {
+        self.topology
+            .neighbours(cell)
+            .into_iter()
+            .filter(|&c| self.get(c) == true)
+            .count()
     }
 
     fn step(&mut self) {
-        let mut next_state = 0;
-        for row in 0..5 {
-            for col in 0..5 {
-                if self.get_tile(row, col) == true {
-                    // It's a bug. Check if it lives or dies.
-                    let count = self.count_adjacent(row, col);
-                    if count == 1 {
-                        // Lives
-                        let idx = row * 5 + col;
-                        next_state |= 1 << idx;
-                    } else {
-                        // Dies. Nothing to modify in the next state.
-                    }
-                } else {
-                    // It's empty. Check if it becomes infested.
-                    let count = self.count_adjacent(row, col);
-                    if count == 1 || count == 2 {
-                        // Infested
-                        let idx = row * 5 + col;
-                        next_state |= 1 << idx;
+        // Only levels with bugs, or next to a level with bugs, can have bugs next time
+        let mut depths = BTreeSet::new();
+        for &depth in self.levels.keys() {
+            for cell in self.cells(depth) {
+                if self.get(cell) == true {
+                    depths.insert(depth);
+                    depths.extend(self.topology.neighbours(cell).iter().map(|c| c.depth));
+                }
+            }
+        }
+
+        let size = (self.topology.width() * self.topology.height()) as usize;
+        let mut levels = BTreeMap::new();
+        for depth in depths {
+            let mut level = vec![false; size];
+            for cell in self.cells(depth) {
+                let bug = self.rules.next(self.get(cell), self.count_adjacent(cell));
+                level[self.index(cell)] = bug;
+            }
+            if level.contains(&true) == true {
+                levels.insert(depth, level);
+            }
+        }
+        self.levels = levels;
+    }
+
+    // Step until a layout shows up for the second time, stopping on that layout
+    fn run_until_repeat(&mut self) -> Cycle {
+        let mut seen: HashMap<BTreeMap<i32, Vec<bool>>, usize> = HashMap::new();
+        let mut minute = 0;
+        loop {
+            if let Some(&start) = seen.get(&self.levels) {
+                return Cycle {
+                    start,
+                    length: minute - start,
+                };
+            }
+            seen.insert(self.levels.clone(), minute);
+            self.step();
+            minute += 1;
+        }
+    }
+
+    fn count_bugs(&self) -> usize {
+        self.levels
+            .values()
+            .map(|level| level.iter().filter(|&&b| b == true).count())
+            .sum()
+    }
+
+    // Each tile on level 0 is worth the next power of two, reading left to right then top to bottom
+    fn biodiversity(&self) -> u64 {
+        assert!(self.topology.width() * self.topology.height() <= 64);
+        self.cells(0)
+            .into_iter()
+            .filter(|&cell| self.get(cell) == true)
+            .map(|cell| 1 << self.index(cell))
+            .sum()
+    }
+
+    fn display(&self) {
+        for &depth in self.levels.keys() {
+            println!("Depth: {}", depth);
+            for row in 0..self.topology.height() {
+                for col in 0..self.topology.width() {
+                    let cell = Cell { depth, row, col };
+                    if self.topology.contains(row, col) == false {
+                        print!("?");
+                    } else if self.get(cell) == false {
+                        print!(".");
                     } else {
-                        // Nothing happens.
+                        print!("#");
                     }
                 }
+                println!();
+            }
+            println!();
+        }
+        println!();
+    }
+}
+
+// A 5x5 grid packed into the low 25 bits, tile (row, col) at bit row * 5 + col
+const GRID: u32 = (1 << 25) - 1;
+const COL_LEFT: u32 = 0b00001_00001_00001_00001_00001;
+const COL_RIGHT: u32 = COL_LEFT << 4;
+
+// Bit-sliced count of adjacent bugs for every tile at once. The bugs' rules only care about
+// counts of 1 and 2, so anything from 3 up lands in `more`.
+#[derive(Default)]
+struct Adjacent {
+    ones: u32,
+    twos: u32,
+    more: u32,
+}
+
+impl Adjacent {
+    fn add(&mut self, tiles: u32) {
+        let carry = self.ones & tiles;
+        self.ones ^= tiles;
+        self.more |= self.twos & carry;
+        self.twos ^= carry;
+    }
+
+    fn one(&self) -> u32 {
+        self.ones & !self.twos & !self.more
+    }
+
+    fn two(&self) -> u32 {
+        !self.ones & self.twos & !self.more
+    }
+}
+
+// Same bugs as Automaton<Flat> on a 5x5 grid with the bugs' rules, stepping the whole grid
+// with shifts and masks
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Bitboard {
+    board: u32,
+}
+
+impl Bitboard {
+    fn from_string(input: &str) -> Self {
+        let mut board = 0;
+        let mut idx = 0;
+        for c in input.chars() {
+            match c {
+                '.' => idx += 1,
+                '#' => {
+                    board |= 1 << idx;
+                    idx += 1;
+                }
+                '\r' | '\n' => (),
+                _ => panic!("Unknown input: 0x{:02x}", c as u32),
             }
         }
+        assert_eq!(idx, 25, "Bitboard needs a 5x5 grid");
 
-        self.state = next_state;
+        Self { board }
     }
 
-    fn run_until_repeat(&mut self) {
-        let mut state_set: HashSet<i32> = HashSet::new();
-        while state_set.insert(self.state) == true {
-            // If the set already had the value, we have found a repeat, the end condition.
+    fn step(&mut self) {
+        let board = self.board;
+        let mut adjacent = Adjacent::default();
+        adjacent.add((board << 5) & GRID); // Tile above
+        adjacent.add(board >> 5); // Tile below
+        adjacent.add((board << 1) & !COL_LEFT & GRID); // Tile to the left
+        adjacent.add((board >> 1) & !COL_RIGHT); // Tile to the right
+
+        let next = (board & adjacent.one()) | (!board & (adjacent.one() | adjacent.two()));
+        self.board = next & GRID;
+    }
+
+    // The 25 bits are the whole state, so they're all that needs remembering
+    fn run_until_repeat(&mut self) -> Cycle {
+        let mut seen: HashMap<u32, usize> = HashMap::new();
+        let mut minute = 0;
+        loop {
+            if let Some(&start) = seen.get(&self.board) {
+                return Cycle {
+                    start,
+                    length: minute - start,
+                };
+            }
+            seen.insert(self.board, minute);
             self.step();
+            minute += 1;
         }
     }
 
-    fn biodiversity(&self) -> i32 {
-        self.state
+    fn count_bugs(&self) -> usize {
+        self.board.count_ones() as usize
+    }
+
+    fn biodiversity(&self) -> u64 {
+        self.board as u64
+    }
+
+    // Same layout as Automaton::levels, for comparing the two
+    fn to_levels(&self) -> BTreeMap<i32, Vec<bool>> {
+        let mut levels = BTreeMap::new();
+        if self.board != 0 {
+            let tiles = (0..25).map(|idx| self.board & (1 << idx) != 0).collect();
+            levels.insert(0, tiles);
+        }
+        levels
     }
 }
 
+type BugSim = Automaton<Flat>;
+
 #[aoc(day24, part1)]
-pub fn solve(input: &str) -> i32 {
-    let mut sim = BugSim::from_string(&input);
+pub fn solve(input: &str) -> u64 {
+    let mut sim = BugSim::from_string(input);
     sim.run_until_repeat();
     //sim.display();
     let biodiversity = sim.biodiversity();
@@ -180,6 +482,13 @@ pub fn solve(input: &str) -> i32 {
     biodiversity
 }
 
+#[aoc(day24, part1, Bitboard)]
+pub fn solve_bitboard(input: &str) -> u64 {
+    let mut sim = Bitboard::from_string(input);
+    sim.run_until_repeat();
+    sim.biodiversity()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -258,8 +567,15 @@ mod test {
 .#...
 ";
         let sim_check = BugSim::from_string(&input);
-        sim.run_until_repeat();
+        let cycle = sim.run_until_repeat();
         assert_eq!(sim, sim_check);
+        assert_eq!(
+            cycle,
+            Cycle {
+                start: 74,
+                length: 12
+            }
+        );
     }
 
     #[test]
@@ -274,4 +590,97 @@ mod test {
         let sim = BugSim::from_string(&input);
         assert_eq!(sim.biodiversity(), 2129920);
     }
+
+    fn sorted_neighbours<T: Topology>(topology: &T, depth: i32, row: i32, col: i32) -> Vec<Cell> {
+        let mut cells = topology.neighbours(Cell { depth, row, col });
+        cells.sort();
+        cells
+    }
+
+    fn cells(list: &[(i32, i32, i32)]) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = list
+            .iter()
+            .map(|&(depth, row, col)| Cell { depth, row, col })
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let flat = Flat::with_size(5, 5);
+        assert_eq!(
+            sorted_neighbours(&flat, 0, 0, 4),
+            cells(&[(0, 0, 3), (0, 1, 4)])
+        );
+        assert_eq!(
+            sorted_neighbours(&flat, 0, 2, 2),
+            cells(&[(0, 1, 2), (0, 2, 1), (0, 2, 3), (0, 3, 2)])
+        );
+
+        let torus = Torus::with_size(5, 3);
+        assert_eq!(
+            sorted_neighbours(&torus, 0, 0, 4),
+            cells(&[(0, 0, 0), (0, 0, 3), (0, 1, 4), (0, 2, 4)])
+        );
+    }
+
+    #[test]
+    fn test_rules() {
+        // A single bug on a torus that only spreads into empty tiles with exactly one neighbour
+        let input = "
+.....
+..#..
+.....
+";
+        let mut sim = Automaton::<Torus>::from_string(input).with_rules(Rules::new(&[1], &[]));
+        sim.step();
+        let check = Automaton::<Torus>::from_string(
+            "
+..#..
+.#.#.
+..#..
+",
+        )
+        .with_rules(Rules::new(&[1], &[]));
+        assert_eq!(sim, check);
+        assert_eq!(sim.count_bugs(), 4);
+
+        // Bugs that never die fill up the torus and then stay put
+        let mut sim = Automaton::<Torus>::from_string(input)
+            .with_rules(Rules::new(&[1, 2, 3, 4], &[0, 1, 2, 3, 4]));
+        let cycle = sim.run_until_repeat();
+        assert_eq!(
+            cycle,
+            Cycle {
+                start: 3,
+                length: 1
+            }
+        );
+        assert_eq!(sim.count_bugs(), 15);
+    }
+
+    #[test]
+    fn test_bitboard() {
+        let input = "
+....#
+#..#.
+#..##
+..#..
+#....
+";
+        let mut sim = BugSim::from_string(input);
+        let mut bitboard = Bitboard::from_string(input);
+        for _ in 0..20 {
+            assert_eq!(bitboard.to_levels(), sim.levels);
+            sim.step();
+            bitboard.step();
+        }
+
+        let mut sim = BugSim::from_string(input);
+        let mut bitboard = Bitboard::from_string(input);
+        assert_eq!(bitboard.run_until_repeat(), sim.run_until_repeat());
+        assert_eq!(bitboard.biodiversity(), 2129920);
+        assert_eq!(bitboard.count_bugs(), 2);
+    }
 }
@@ -160,238 +160,458 @@
     Starting with your scan, how many bugs are present after 200 minutes?
 */
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-#[derive(Debug, Eq, PartialEq)]
-struct BugSim {
-    state: HashMap<i32, i32>,
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Cell {
+    depth: i32,
+    row: i32,
+    col: i32,
 }
 
-impl BugSim {
-    fn from_string(input: &str) -> Self {
-        let mut state: i32 = 0;
-        let mut idx = 0;
-        for c in input.chars() {
-            if c == '.' || c == '?' {
-                // Empty space. Do nothing as the value is already set to 0.
-                idx += 1;
-            } else if c == '#' {
-                // Bug. Set to 1.
-                state |= 1 << idx;
-                idx += 1;
-            } else if c == '\r' || c == '\n' {
-                // Line break, just consume
+// Decides which tiles exist and which tiles count as adjacent to each other
+trait Topology {
+    fn with_size(width: i32, height: i32) -> Self
+    where
+        Self: Sized;
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn contains(&self, row: i32, col: i32) -> bool {
+        (0..self.height()).contains(&row) && (0..self.width()).contains(&col)
+    }
+    fn neighbours(&self, cell: Cell) -> Vec<Cell>;
+}
+
+// The middle tile holds the next level down (depth + 1) and the whole grid sits in the
+// middle tile of the level above (depth - 1). Needs an odd width and height.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Recursive {
+    width: i32,
+    height: i32,
+}
+
+impl Recursive {
+    fn centre(&self) -> (i32, i32) {
+        (self.height / 2, self.width / 2)
+    }
+}
+
+impl Topology for Recursive {
+    fn with_size(width: i32, height: i32) -> Self {
+        assert!(
+            width % 2 == 1 && height % 2 == 1,
+            "Recursive grid must have a middle tile, not {}x{}",
+            width,
+            height
+        );
+        Self { width, height }
+    }
+
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn contains(&self, row: i32, col: i32) -> bool {
+        (0..self.height).contains(&row)
+            && (0..self.width).contains(&col)
+            && (row, col) != self.centre()
+    }
+
+    fn neighbours(&self, cell: Cell) -> Vec<Cell> {
+        let (centre_row, centre_col) = self.centre();
+        let mut cells = Vec::new();
+        for &(dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter() {
+            let (row, col) = (cell.row + dr, cell.col + dc);
+            if self.contains(row, col) == true {
+                // Nothing special, just the next tile over
+                cells.push(Cell {
+                    depth: cell.depth,
+                    row,
+                    col,
+                });
+            } else if (row, col) == (centre_row, centre_col) {
+                // Stepping into the middle tile touches the whole facing edge of the level below
+                let edge: Vec<(i32, i32)> = match (dr, dc) {
+                    (1, _) => (0..self.width).map(|c| (0, c)).collect(),
+                    (-1, _) => (0..self.width).map(|c| (self.height - 1, c)).collect(),
+                    (_, 1) => (0..self.height).map(|r| (r, 0)).collect(),
+                    _ => (0..self.height).map(|r| (r, self.width - 1)).collect(),
+                };
+                cells.extend(edge.into_iter().map(|(row, col)| Cell {
+                    depth: cell.depth + 1,
+                    row,
+                    col,
+                }));
             } else {
-                panic!("Unknown input: 0x{:02x}", c as u8);
+                // Stepping off the edge touches the tile next to the middle in the level above
+                cells.push(Cell {
+                    depth: cell.depth - 1,
+                    row: centre_row + dr,
+                    col: centre_col + dc,
+                });
             }
         }
+        cells
+    }
+}
 
-        let mut state_map = HashMap::new();
-        state_map.insert(0, state);
+// How many adjacent bugs make an empty tile infested, and how many let a bug survive
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rules {
+    birth: Vec<usize>,
+    survival: Vec<usize>,
+}
 
-        Self { state: state_map }
+impl Rules {
+    fn new(birth: &[usize], survival: &[usize]) -> Self {
+        Self {
+            birth: birth.to_vec(),
+            survival: survival.to_vec(),
+        }
     }
 
-    fn get_tile(&self, depth: i32, row: i32, col: i32) -> Option<bool> {
-        if (0..5).contains(&row) == false
-            || (0..5).contains(&col) == false
-            || (row == 2 && col == 2)
-        {
-            return None; // Invalid tiles may result from looking beyond the grid boundaries, or from the recursive tile
-        }
+    fn bugs() -> Self {
+        Self::new(&[1, 2], &[1])
+    }
 
-        if let Some(state) = self.state.get(&depth) {
-            let idx = row * 5 + col;
-            let tile_is_bug = (state & (1 << idx)) != 0;
-            Some(tile_is_bug)
+    fn next(&self, alive: bool, adjacent: usize) -> bool {
+        if alive == true {
+            self.survival.contains(&adjacent)
         } else {
-            Some(false) // If depth isn't in map, then it hasn't been touched before and is empty
+            self.birth.contains(&adjacent)
         }
     }
+}
 
-    fn display(&self) {
-        let mut keys: Vec<i32> = self.state.keys().cloned().collect();
-        keys.sort_unstable();
-        for k in keys {
-            println!("Depth: {}", k);
-            for row in 0..5 {
-                for col in 0..5 {
-                    if let Some(tile) = self.get_tile(k, row, col) {
-                        if tile == false {
-                            print!(".");
-                        } else {
-                            print!("#");
-                        }
-                    } else {
-                        print!("?");
-                    }
+// Levels with no bugs are left out, so two automatons with the same bugs compare equal
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Automaton<T: Topology> {
+    topology: T,
+    rules: Rules,
+    levels: BTreeMap<i32, Vec<bool>>,
+}
+
+impl<T: Topology> Automaton<T> {
+    fn new(topology: T, rules: Rules) -> Self {
+        Self {
+            topology,
+            rules,
+            levels: BTreeMap::new(),
+        }
+    }
+
+    // Level 0 from a scan. The grid size comes from the scan and the rules default to the bugs' rules.
+    fn from_string(input: &str) -> Self {
+        let lines: Vec<&str> = input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| line.is_empty() == false)
+            .collect();
+        let height = lines.len() as i32;
+        let width = lines.first().map_or(0, |line| line.chars().count()) as i32;
+
+        let mut sim = Self::new(T::with_size(width, height), Rules::bugs());
+        for (row, line) in lines.iter().enumerate() {
+            assert_eq!(line.chars().count() as i32, width, "Ragged line: {}", line);
+            for (col, c) in line.chars().enumerate() {
+                let cell = Cell {
+                    depth: 0,
+                    row: row as i32,
+                    col: col as i32,
+                };
+                match c {
+                    '.' | '?' => (),
+                    '#' => sim.set(cell, true),
+                    _ => panic!("Unknown input: 0x{:02x}", c as u32),
                 }
-                println!();
             }
-            println!();
         }
-        println!();
+        sim
     }
 
-    fn count_adjacent(&self, depth: i32, row: i32, col: i32) -> i32 {
-        let mut tiles_to_check: Vec<(i32, i32, i32)> = Vec::new(); // (depth, row, col)
+    fn index(&self, cell: Cell) -> usize {
+        (cell.row * self.topology.width() + cell.col) as usize
+    }
 
-        // Up
-        if row - 1 < 0 {
-            // Adjacent to tile 8 on the diagram
-            tiles_to_check.push((depth - 1, 1, 2));
-        } else if row - 1 == 2 && col == 2 {
-            // Adjacent to bottom row of center tile
-            for up_col in 0..5 {
-                tiles_to_check.push((depth + 1, 4, up_col));
-            }
-        } else {
-            // Nothing special, just the tile above
-            tiles_to_check.push((depth, row - 1, col));
+    fn get(&self, cell: Cell) -> bool {
+        if self.topology.contains(cell.row, cell.col) == false {
+            return false;
         }
+        match self.levels.get(&cell.depth) {
+            Some(level) => level[self.index(cell)],
+            None => false, // If depth isn't in map, then it hasn't been touched before and is empty
+        }
+    }
+
+    fn set(&mut self, cell: Cell, bug: bool) {
+        if self.topology.contains(cell.row, cell.col) == false {
+            return;
+        }
+        let idx = self.index(cell);
+        let size = (self.topology.width() * self.topology.height()) as usize;
+        let level = self
+            .levels
+            .entry(cell.depth)
+            .or_insert_with(|| vec![false; size]);
+        level[idx] = bug;
+        if level.contains(&true) == false {
+            self.levels.remove(&cell.depth);
+        }
+    }
 
-        // Down
-        if row + 1 >= 5 {
-            // Adjacent to tile 18 on the diagram
-            tiles_to_check.push((depth - 1, 3, 2));
-        } else if row + 1 == 2 && col == 2 {
-            // Adjacent to top row of center tile
-            for down_col in 0..5 {
-                tiles_to_check.push((depth + 1, 0, down_col));
+    fn cells(&self, depth: i32) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        for row in 0..self.topology.height() {
+            for col in 0..self.topology.width() {
+                if self.topology.contains(row, col) == true {
+                    cells.push(Cell { depth, row, col });
+                }
             }
-        } else {
-            // Nothing special, just the tile below
-            tiles_to_check.push((depth, row + 1, col));
         }
+        cells
+    }
+
+    fn count_adjacent(&self, cell: Cell) -> usize {
+        self.topology
+            .neighbours(cell)
+            .into_iter()
+            .filter(|&c| self.get(c) == true)
+            .count()
+    }
 
-        // Left
-        if col - 1 < 0 {
-            // Adjacent to tile 12 on the diagram
-            tiles_to_check.push((depth - 1, 2, 1));
-        } else if row == 2 && col - 1 == 2 {
-            // Adjacent to rightmost row of center tile
-            for left_row in 0..5 {
-                tiles_to_check.push((depth + 1, left_row, 4));
+    fn step(&mut self) {
+        // Only levels with bugs, or next to a level with bugs, can have bugs next time
+        let mut depths = BTreeSet::new();
+        for &depth in self.levels.keys() {
+            for cell in self.cells(depth) {
+                if self.get(cell) == true {
+                    depths.insert(depth);
+                    depths.extend(self.topology.neighbours(cell).iter().map(|c| c.depth));
+                }
             }
-        } else {
-            // Nothing special, just the tile to the left
-            tiles_to_check.push((depth, row, col - 1));
         }
 
-        // Right
-        if col + 1 >= 5 {
-            // Adjacent to tile 14 on the diagram
-            tiles_to_check.push((depth - 1, 2, 3));
-        } else if row == 2 && col + 1 == 2 {
-            // Adjacent to leftmost row of center tile
-            for right_row in 0..5 {
-                tiles_to_check.push((depth + 1, right_row, 0));
+        let size = (self.topology.width() * self.topology.height()) as usize;
+        let mut levels = BTreeMap::new();
+        for depth in depths {
+            let mut level = vec![false; size];
+            for cell in self.cells(depth) {
+                let bug = self.rules.next(self.get(cell), self.count_adjacent(cell));
+                level[self.index(cell)] = bug;
+            }
+            if level.contains(&true) == true {
+                levels.insert(depth, level);
             }
-        } else {
-            // Nothing special, just the tile to the right
-            tiles_to_check.push((depth, row, col + 1));
         }
+        self.levels = levels;
+    }
 
-        let count = tiles_to_check
-            .iter()
-            .filter(|(d, r, c)| self.get_tile(*d, *r, *c) == Some(true))
-            .count();
-        count as i32
-    }
-
-    fn step_layer(&mut self, depth: i32) -> i32 {
-        let mut next_state = 0;
-        for row in 0..5 {
-            for col in 0..5 {
-                if let Some(tile) = self.get_tile(depth, row, col) {
-                    if tile == true {
-                        // It's a bug. Check if it lives or dies.
-                        let count = self.count_adjacent(depth, row, col);
-                        if count == 1 {
-                            // Lives
-                            let idx = row * 5 + col;
-                            next_state |= 1 << idx;
-                        } else {
-                            // Dies. Nothing to modify in the next state.
-                        }
+    fn step_many(&mut self, num_steps: usize) {
+        (0..num_steps).for_each(|_| self.step());
+    }
+
+    fn count_bugs(&self) -> usize {
+        self.levels
+            .values()
+            .map(|level| level.iter().filter(|&&b| b == true).count())
+            .sum()
+    }
+
+    fn display(&self) {
+        for &depth in self.levels.keys() {
+            println!("Depth: {}", depth);
+            for row in 0..self.topology.height() {
+                for col in 0..self.topology.width() {
+                    let cell = Cell { depth, row, col };
+                    if self.topology.contains(row, col) == false {
+                        print!("?");
+                    } else if self.get(cell) == false {
+                        print!(".");
                     } else {
-                        // It's empty. Check if it becomes infested.
-                        let count = self.count_adjacent(depth, row, col);
-                        if count == 1 || count == 2 {
-                            // Infested
-                            let idx = row * 5 + col;
-                            next_state |= 1 << idx;
-                        } else {
-                            // Nothing happens.
-                        }
+                        print!("#");
                     }
                 }
+                println!();
             }
+            println!();
         }
+        println!();
+    }
+}
 
-        next_state
+// A 5x5 level packed into the low 25 bits, tile (row, col) at bit row * 5 + col
+const GRID: u32 = (1 << 25) - 1;
+const CENTRE: u32 = 1 << 12;
+const ROW_TOP: u32 = 0b11111;
+const ROW_BOTTOM: u32 = ROW_TOP << 20;
+const COL_LEFT: u32 = 0b00001_00001_00001_00001_00001;
+const COL_RIGHT: u32 = COL_LEFT << 4;
+const ABOVE_CENTRE: u32 = 1 << 7;
+const BELOW_CENTRE: u32 = 1 << 17;
+const LEFT_OF_CENTRE: u32 = 1 << 11;
+const RIGHT_OF_CENTRE: u32 = 1 << 13;
+
+// Bit-sliced count of adjacent bugs for every tile at once. The bugs' rules only care about
+// counts of 1 and 2, so anything from 3 up lands in `more`.
+#[derive(Default)]
+struct Adjacent {
+    ones: u32,
+    twos: u32,
+    more: u32,
+}
+
+impl Adjacent {
+    fn add(&mut self, tiles: u32) {
+        let carry = self.ones & tiles;
+        self.ones ^= tiles;
+        self.more |= self.twos & carry;
+        self.twos ^= carry;
     }
 
-    fn step_single(&mut self) {
-        let mut next_state_map = HashMap::new();
+    fn one(&self) -> u32 {
+        self.ones & !self.twos & !self.more
+    }
 
-        // For each existing layer, calculate the next state
-        let keys = self.state.keys().cloned().collect::<Vec<i32>>();
-        for &k in &keys {
-            let next_state = self.step_layer(k);
-            next_state_map.insert(k, next_state);
+    fn two(&self) -> u32 {
+        !self.ones & self.twos & !self.more
+    }
+}
+
+// Same bugs as Automaton<Recursive> on a 5x5 grid with the bugs' rules, stepping whole levels
+// with shifts and masks
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Bitboard {
+    min_depth: i32,
+    levels: Vec<u32>,
+}
+
+impl Bitboard {
+    fn from_string(input: &str) -> Self {
+        let mut board = 0;
+        let mut idx = 0;
+        for c in input.chars() {
+            match c {
+                '.' | '?' => idx += 1,
+                '#' if idx == 12 => idx += 1, // The middle tile is the next level down
+                '#' => {
+                    board |= 1 << idx;
+                    idx += 1;
+                }
+                '\r' | '\n' => (),
+                _ => panic!("Unknown input: 0x{:02x}", c as u32),
+            }
         }
+        assert_eq!(idx, 25, "Bitboard needs a 5x5 grid");
 
-        // Also calculate the next state for the layer below the minimum and above the max.
-        // These are empty layers adjacent to non-empty ones which may become non-empty.
-        let min_depth = *keys.iter().min().unwrap();
-        let next_state = self.step_layer(min_depth - 1);
-        if next_state != 0 {
-            next_state_map.insert(min_depth - 1, next_state);
+        Self {
+            min_depth: 0,
+            levels: vec![board],
         }
+    }
 
-        let max_depth = *keys.iter().max().unwrap();
-        let next_state = self.step_layer(max_depth + 1);
-        if next_state != 0 {
-            next_state_map.insert(max_depth + 1, next_state);
+    fn step_level(board: u32, outer: u32, inner: u32) -> u32 {
+        let mut adjacent = Adjacent::default();
+        adjacent.add((board << 5) & GRID); // Tile above
+        adjacent.add(board >> 5); // Tile below
+        adjacent.add((board << 1) & !COL_LEFT & GRID); // Tile to the left
+        adjacent.add((board >> 1) & !COL_RIGHT); // Tile to the right
+
+        // Edges of this level touch the tiles around the middle of the level above
+        let edges = [
+            (ABOVE_CENTRE, ROW_TOP),
+            (BELOW_CENTRE, ROW_BOTTOM),
+            (LEFT_OF_CENTRE, COL_LEFT),
+            (RIGHT_OF_CENTRE, COL_RIGHT),
+        ];
+        for &(tile, edge) in edges.iter() {
+            if outer & tile != 0 {
+                adjacent.add(edge);
+            }
+            // And the tiles around the middle touch the facing edge of the level below
+            for _ in 0..(inner & edge).count_ones().min(3) {
+                adjacent.add(tile);
+            }
         }
 
-        self.state = next_state_map;
+        let next = (board & adjacent.one()) | (!board & (adjacent.one() | adjacent.two()));
+        next & GRID & !CENTRE
     }
 
-    fn step(&mut self, num_steps: i32) {
-        (0..num_steps).for_each(|_| self.step_single());
+    fn step(&mut self) {
+        // Room for bugs to spread one level further out and in
+        self.levels.insert(0, 0);
+        self.levels.push(0);
+        self.min_depth -= 1;
+
+        let last = self.levels.len() - 1;
+        let next: Vec<u32> = (0..=last)
+            .map(|i| {
+                let outer = if i > 0 { self.levels[i - 1] } else { 0 };
+                let inner = if i < last { self.levels[i + 1] } else { 0 };
+                Bitboard::step_level(self.levels[i], outer, inner)
+            })
+            .collect();
+        self.levels = next;
+
+        while self.levels.len() > 1 && self.levels[0] == 0 {
+            self.levels.remove(0);
+            self.min_depth += 1;
+        }
+        while self.levels.len() > 1 && self.levels[self.levels.len() - 1] == 0 {
+            self.levels.pop();
+        }
     }
 
-    fn count_bugs(&self) -> i32 {
-        let mut count = 0;
-        for &depth in self.state.keys() {
-            for row in 0..5 {
-                for col in 0..5 {
-                    if self.get_tile(depth, row, col) == Some(true) {
-                        count += 1;
-                    }
-                }
-            }
-        }
+    fn step_many(&mut self, num_steps: usize) {
+        (0..num_steps).for_each(|_| self.step());
+    }
+
+    fn count_bugs(&self) -> usize {
+        self.levels.iter().map(|b| b.count_ones() as usize).sum()
+    }
 
-        count
+    // Same layout as Automaton::levels, for comparing the two
+    fn to_levels(&self) -> BTreeMap<i32, Vec<bool>> {
+        self.levels
+            .iter()
+            .enumerate()
+            .filter(|(_, &board)| board != 0)
+            .map(|(i, &board)| {
+                let tiles = (0..25).map(|idx| board & (1 << idx) != 0).collect();
+                (self.min_depth + i as i32, tiles)
+            })
+            .collect()
     }
 }
 
+type BugSim = Automaton<Recursive>;
+
 #[aoc(day24, part2)]
-pub fn solve(input: &str) -> i32 {
-    let mut sim = BugSim::from_string(&input);
-    sim.step(200);
+pub fn solve(input: &str) -> usize {
+    let mut sim = BugSim::from_string(input);
+    sim.step_many(200);
     //sim.display();
     let total_bugs = sim.count_bugs();
     println!("Total bugs: {}", total_bugs);
     total_bugs
 }
 
+#[aoc(day24, part2, Bitboard)]
+pub fn solve_bitboard(input: &str) -> usize {
+    let mut sim = Bitboard::from_string(input);
+    sim.step_many(200);
+    sim.count_bugs()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::fs;
+    use std::time::Instant;
 
     #[test]
     fn test_step() {
@@ -514,9 +734,7 @@ mod test {
 ";
         let sim_check_5 = BugSim::from_string(&input);
 
-        let mut sim_check = BugSim {
-            state: HashMap::new(),
-        };
+        let mut sim_check = BugSim::new(Recursive::with_size(5, 5), Rules::bugs());
         let layers = [
             (-5, sim_check_n5),
             (-4, sim_check_n4),
@@ -531,10 +749,10 @@ mod test {
             (5, sim_check_5),
         ];
         for (i, l) in &layers {
-            sim_check.state.insert(*i, *l.state.get(&0).unwrap());
+            sim_check.levels.insert(*i, l.levels[&0].clone());
         }
 
-        sim.step(10);
+        sim.step_many(10);
         assert_eq!(sim, sim_check);
     }
 
@@ -548,7 +766,138 @@ mod test {
 #....
 ";
         let mut sim = BugSim::from_string(&input);
-        sim.step(10);
+        sim.step_many(10);
         assert_eq!(sim.count_bugs(), 99);
     }
+
+    fn sorted_neighbours<T: Topology>(topology: &T, depth: i32, row: i32, col: i32) -> Vec<Cell> {
+        let mut cells = topology.neighbours(Cell { depth, row, col });
+        cells.sort();
+        cells
+    }
+
+    fn cells(list: &[(i32, i32, i32)]) -> Vec<Cell> {
+        let mut cells: Vec<Cell> = list
+            .iter()
+            .map(|&(depth, row, col)| Cell { depth, row, col })
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn test_neighbours() {
+        // Examples from the puzzle, where the numbered tiles are depth 0 and the lettered ones depth 1
+        let recursive = Recursive::with_size(5, 5);
+        assert_eq!(
+            sorted_neighbours(&recursive, 0, 3, 3),
+            cells(&[(0, 2, 3), (0, 3, 2), (0, 3, 4), (0, 4, 3)])
+        );
+        assert_eq!(
+            sorted_neighbours(&recursive, 1, 0, 3),
+            cells(&[(0, 1, 2), (1, 0, 2), (1, 0, 4), (1, 1, 3)])
+        );
+        assert_eq!(
+            sorted_neighbours(&recursive, 1, 0, 4),
+            cells(&[(0, 1, 2), (1, 0, 3), (0, 2, 3), (1, 1, 4)])
+        );
+        assert_eq!(
+            sorted_neighbours(&recursive, 0, 2, 3),
+            cells(&[
+                (0, 1, 3),
+                (1, 0, 4),
+                (1, 1, 4),
+                (1, 2, 4),
+                (1, 3, 4),
+                (1, 4, 4),
+                (0, 2, 4),
+                (0, 3, 3),
+            ])
+        );
+        assert!(recursive.contains(2, 2) == false);
+
+        let recursive = Recursive::with_size(3, 5);
+        assert_eq!(
+            sorted_neighbours(&recursive, 0, 1, 1),
+            cells(&[
+                (0, 0, 1),
+                (0, 1, 0),
+                (0, 1, 2),
+                (1, 0, 0),
+                (1, 0, 1),
+                (1, 0, 2)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_levels() {
+        // Recursive levels only appear once bugs reach them
+        let input = "
+.....
+#....
+.....
+";
+        let mut sim = BugSim::from_string(input);
+        sim.step();
+        assert_eq!(
+            sim.levels.keys().cloned().collect::<Vec<i32>>(),
+            vec![-1, 0]
+        );
+    }
+
+    #[test]
+    fn test_bitboard() {
+        let input = "
+....#
+#..#.
+#..##
+..#..
+#....
+";
+        let mut bitboard = Bitboard::from_string(input);
+        bitboard.step_many(10);
+        assert_eq!(bitboard.count_bugs(), 99);
+        assert_eq!(bitboard.min_depth, -5);
+        assert_eq!(bitboard.levels.len(), 11);
+
+        let input = fs::read_to_string("input/2019/day24.txt")
+            .expect("Something went wrong reading the file");
+        let mut sim = BugSim::from_string(&input);
+        let mut bitboard = Bitboard::from_string(&input);
+        for _ in 0..60 {
+            assert_eq!(bitboard.to_levels(), sim.levels);
+            sim.step();
+            bitboard.step();
+        }
+    }
+
+    // Timing for the engine against the bitboard. Run with:
+    // cargo test --release bench_step -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_step() {
+        let input = fs::read_to_string("input/2019/day24.txt")
+            .expect("Something went wrong reading the file");
+        for &minutes in [200, 400, 800].iter() {
+            let start = Instant::now();
+            let mut sim = BugSim::from_string(&input);
+            sim.step_many(minutes);
+            let engine_time = start.elapsed();
+
+            let start = Instant::now();
+            let mut bitboard = Bitboard::from_string(&input);
+            bitboard.step_many(minutes);
+            let bitboard_time = start.elapsed();
+
+            assert_eq!(bitboard.count_bugs(), sim.count_bugs());
+            println!(
+                "{} minutes, {} bugs: engine {:?}, bitboard {:?}",
+                minutes,
+                sim.count_bugs(),
+                engine_time,
+                bitboard_time
+            );
+        }
+    }
 }
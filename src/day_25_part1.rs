@@ -25,6 +25,7 @@
 
 use std::collections::HashMap;
 use std::fmt;
+use std::fs;
 use std::io::{self, BufRead};
 
 #[derive(Clone)]
@@ -305,6 +306,7 @@ impl Program {
     }
 }
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 enum Command {
     North,
     South,
@@ -313,20 +315,54 @@ enum Command {
     Take(String),
     Drop(String),
     List,
+    Goto(String),
+    Save(String),
+    Load(String),
+    Define(String, String), // Macro name and the line it expands to
+    Expect(String),
     Unknown,
 }
 
 impl Command {
     fn from_string(input: &str) -> Self {
-        let pieces: Vec<&str> = input.trim().split(' ').collect();
-        match pieces[0] {
-            "north" => Self::North,
-            "south" => Self::South,
-            "east" => Self::East,
-            "west" => Self::West,
-            "take" => Self::Take(pieces[1..].join(" ")),
-            "drop" => Self::Drop(pieces[1..].join(" ")),
-            "inv" => Self::List,
+        let input = input.trim();
+        let (word, rest) = match input.find(' ') {
+            Some(idx) => (&input[..idx], input[idx + 1..].trim()),
+            None => (input, ""),
+        };
+        match word {
+            "north" | "n" => Self::North,
+            "south" | "s" => Self::South,
+            "east" | "e" => Self::East,
+            "west" | "w" => Self::West,
+            "take" => Self::Take(rest.to_owned()),
+            "drop" => Self::Drop(rest.to_owned()),
+            "inv" | "i" => Self::List,
+            "goto" => Self::Goto(rest.to_owned()),
+            "save" => Self::Save(rest.to_owned()),
+            "load" => Self::Load(rest.to_owned()),
+            "expect" => Self::Expect(rest.to_owned()),
+            "define" => match rest.find('=') {
+                Some(idx) => Self::Define(
+                    rest[..idx].trim().to_owned(),
+                    rest[idx + 1..].trim().to_owned(),
+                ),
+                None => Self::Unknown,
+            },
+            _ => Self::Unknown,
+        }
+    }
+
+    fn is_movement(&self) -> bool {
+        matches!(self, Self::North | Self::South | Self::East | Self::West)
+    }
+
+    fn opposite(&self) -> Self {
+        match self {
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::East => Self::West,
+            Self::West => Self::East,
             _ => Self::Unknown,
         }
     }
@@ -342,21 +378,188 @@ impl fmt::Display for Command {
             Self::Take(s) => write!(f, "take {}", s),
             Self::Drop(s) => write!(f, "drop {}", s),
             Self::List => write!(f, "inv"),
+            Self::Goto(s) => write!(f, "goto {}", s),
+            Self::Save(s) => write!(f, "save {}", s),
+            Self::Load(s) => write!(f, "load {}", s),
+            Self::Define(name, body) => write!(f, "define {} = {}", name, body),
+            Self::Expect(s) => write!(f, "expect {}", s),
             Self::Unknown => write!(f, ""),
         }
     }
 }
 
+const OUTPUT_LIMIT: usize = 10_000; // Any more than this and the program is probably stuck in a loop
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Room {
+    name: String,
+    description: String,
+    doors: Vec<Command>,
+    items: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Weight {
+    Heavier,
+    Lighter,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Event {
+    RoomEntered(Room),
+    ItemTaken(String),
+    ItemDropped(String),
+    InventoryListed(Vec<String>),
+    Ejected { heavier_or_lighter: Weight }, // Other droids compared to this one
+    Stuck,
+    GameOver { reason: String },
+    Password(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum List {
+    None,
+    Doors,
+    Items,
+    Inventory,
+}
+
+struct EventParser {
+    events: Vec<Event>,
+    room: Option<Room>,
+    list: List,
+}
+
+impl EventParser {
+    fn parse(output: &str) -> Vec<Event> {
+        let mut parser = Self {
+            events: Vec::new(),
+            room: None,
+            list: List::None,
+        };
+        for line in output.lines() {
+            parser.parse_line(line);
+        }
+        parser.finish_room();
+
+        // The game only stops asking for commands when it's over
+        let prompted = output.trim_end().ends_with("Command?");
+        let finished = parser
+            .events
+            .iter()
+            .any(|e| matches!(e, Event::Password(_)));
+        if prompted == false && finished == false {
+            if let Some(reason) = output.lines().rev().find(|line| line.is_empty() == false) {
+                parser.events.push(Event::GameOver {
+                    reason: reason.to_owned(),
+                });
+            }
+        }
+
+        parser.events
+    }
+
+    fn finish_room(&mut self) {
+        if let Some(room) = self.room.take() {
+            self.events.push(Event::RoomEntered(room));
+        }
+    }
+
+    fn parse_line(&mut self, line: &str) {
+        if line.starts_with("== ") && line.ends_with(" ==") {
+            self.finish_room();
+            self.room = Some(Room {
+                name: line[3..line.len() - 3].to_owned(),
+                ..Room::default()
+            });
+        } else if line == "Doors here lead:" {
+            self.list = List::Doors;
+        } else if line == "Items here:" {
+            self.list = List::Items;
+        } else if line == "Items in your inventory:" {
+            self.finish_room();
+            self.list = List::Inventory;
+            self.events.push(Event::InventoryListed(Vec::new()));
+        } else if let Some(entry) = line.strip_prefix("- ") {
+            match (self.list, self.room.as_mut(), self.events.last_mut()) {
+                (List::Doors, Some(room), _) => room.doors.push(Command::from_string(entry)),
+                (List::Items, Some(room), _) => room.items.push(entry.to_owned()),
+                (List::Inventory, _, Some(Event::InventoryListed(items))) => {
+                    items.push(entry.to_owned())
+                }
+                _ => (),
+            }
+        } else if line.is_empty() == true {
+            self.list = List::None;
+        } else if let Some(room) = self.room.as_mut().filter(|r| r.doors.is_empty() == true) {
+            if room.description.is_empty() == false {
+                room.description.push('\n');
+            }
+            room.description.push_str(line);
+        } else {
+            self.finish_room();
+            self.parse_message(line);
+        }
+    }
+
+    fn parse_message(&mut self, line: &str) {
+        if let Some(item) = line
+            .strip_prefix("You take the ")
+            .and_then(|s| s.strip_suffix('.'))
+        {
+            self.events.push(Event::ItemTaken(item.to_owned()));
+        } else if let Some(item) = line
+            .strip_prefix("You drop the ")
+            .and_then(|s| s.strip_suffix('.'))
+        {
+            self.events.push(Event::ItemDropped(item.to_owned()));
+        } else if line == "You aren't carrying any items." {
+            self.events.push(Event::InventoryListed(Vec::new()));
+        } else if line.contains("ejected back to the checkpoint") {
+            let heavier_or_lighter = if line.contains("heavier") {
+                Weight::Heavier
+            } else {
+                Weight::Lighter
+            };
+            self.events.push(Event::Ejected { heavier_or_lighter });
+        } else if line.contains("You can't move") {
+            self.events.push(Event::Stuck);
+        } else if let Some(password) = find_password(line) {
+            self.events.push(Event::Password(password));
+        }
+    }
+}
+
+fn find_password(output: &str) -> Option<String> {
+    let start = output.find("typing ")? + "typing ".len();
+    let password: String = output[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    if password.is_empty() == true {
+        None
+    } else {
+        Some(password)
+    }
+}
+
+#[derive(Clone)]
 struct Droid {
     program: Program,
-    commands: Vec<Command>,
+    room: Option<Room>,
+    inventory: Vec<String>,
+    password: Option<String>,
+    game_over: bool,
 }
 
 impl Droid {
     fn new(program: Program) -> Self {
         Self {
             program,
-            commands: Vec::new(),
+            room: None,
+            inventory: Vec::new(),
+            password: None,
+            game_over: false,
         }
     }
 
@@ -367,38 +570,386 @@ impl Droid {
         self.program.input.push(0x0A_i64); // Always end with newline
     }
 
-    fn print_output(&mut self) {
-        for i in self.program.output.drain(..) {
-            if i < 128 {
-                // If it's ASCII, print it as a character
-                print!("{}", (i as u8) as char);
-            } else {
-                panic!("Non-ASCII character received");
-            }
-        }
+    fn current_room(&self) -> String {
+        self.room
+            .as_ref()
+            .map(|room| room.name.clone())
+            .unwrap_or_default()
     }
 
-    fn run(&mut self) {
+    fn send(&mut self, command: Command) -> Vec<Event> {
+        self.give_command(command);
+        self.read_events()
+    }
+
+    // Collects output until the droid wants another command
+    fn read_output(&mut self) -> String {
+        let mut output = String::new();
         while self.program.halted == false {
             self.program.run_with_pause();
-            self.print_output();
-
-            if self.program.input_needed == true {
-                if self.commands.is_empty() == false {
-                    // Get command from queue
-                    let command = self.commands.remove(0);
-                    self.give_command(command);
+            for i in self.program.output.drain(..) {
+                if i < 128 {
+                    output.push((i as u8) as char);
                 } else {
-                    // Get command from user
-                    let mut line = String::new();
-                    let stdin = io::stdin();
-                    stdin.lock().read_line(&mut line).unwrap();
-
-                    match Command::from_string(&line) {
-                        Command::Unknown => println!("Unknown command; try again:"),
-                        c => self.give_command(c),
+                    panic!("Non-ASCII character received");
+                }
+            }
+
+            if self.program.input_needed == true || output.len() > OUTPUT_LIMIT {
+                break;
+            }
+        }
+        output
+    }
+
+    fn read_events(&mut self) -> Vec<Event> {
+        let events = EventParser::parse(&self.read_output());
+        self.update(&events);
+        events
+    }
+
+    fn update(&mut self, events: &[Event]) {
+        for event in events {
+            match event {
+                Event::RoomEntered(room) => self.room = Some(room.clone()),
+                Event::ItemTaken(item) => {
+                    self.inventory.push(item.clone());
+                    if let Some(room) = self.room.as_mut() {
+                        room.items.retain(|i| i != item);
+                    }
+                }
+                Event::ItemDropped(item) => {
+                    self.inventory.retain(|i| i != item);
+                    if let Some(room) = self.room.as_mut() {
+                        room.items.push(item.clone());
                     }
                 }
+                Event::InventoryListed(items) => self.inventory = items.clone(),
+                Event::GameOver { .. } => self.game_over = true,
+                Event::Password(password) => self.password = Some(password.clone()),
+                Event::Ejected { .. } | Event::Stuck => (),
+            }
+        }
+    }
+}
+
+fn last_room(events: &[Event]) -> Option<Room> {
+    events.iter().rev().find_map(|e| match e {
+        Event::RoomEntered(room) => Some(room.clone()),
+        _ => None,
+    })
+}
+
+// Which door leads where, as far as the droid has seen
+#[derive(Default)]
+struct ShipMap {
+    links: HashMap<(String, Command), String>,
+}
+
+impl ShipMap {
+    fn contains(&self, from: &str, door: &Command) -> bool {
+        self.links.contains_key(&(from.to_owned(), door.clone()))
+    }
+
+    fn link(&mut self, from: &str, door: &Command, to: &str) {
+        self.links
+            .insert((from.to_owned(), door.clone()), to.to_owned());
+        self.links
+            .insert((to.to_owned(), door.opposite()), from.to_owned());
+    }
+
+    fn route(&self, start: &str, target: &str) -> Option<Vec<Command>> {
+        let mut came_from: HashMap<String, (String, Command)> = HashMap::new();
+        let mut frontier: Vec<String> = vec![start.to_owned()];
+        while frontier.is_empty() == false && came_from.contains_key(target) == false {
+            let mut next_frontier: Vec<String> = Vec::new();
+            for room in frontier {
+                for ((from, door), to) in self.links.iter() {
+                    if *from == room && to != start && came_from.contains_key(to) == false {
+                        came_from.insert(to.clone(), (room.clone(), door.clone()));
+                        next_frontier.push(to.clone());
+                    }
+                }
+            }
+            frontier = next_frontier;
+        }
+
+        let mut path: Vec<Command> = Vec::new();
+        let mut room = target.to_owned();
+        while room != start {
+            let (from, door) = came_from.get(&room)?;
+            path.insert(0, door.clone());
+            room = from.clone();
+        }
+        Some(path)
+    }
+}
+
+const MACRO_DEPTH_LIMIT: usize = 10;
+
+// Several commands can be given on one line, separated by semicolons. A macro definition takes up the whole line, since
+// its body can have semicolons of its own.
+fn split_line(line: &str) -> Vec<&str> {
+    if line.trim_start().starts_with("define ") {
+        return vec![line.trim()];
+    }
+
+    line.split(';')
+        .map(|piece| piece.trim())
+        .filter(|piece| piece.is_empty() == false)
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct SessionError {
+    line: usize,
+    expected: String,
+    output: String,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: expected \"{}\" in output:\n{}",
+            self.line, self.expected, self.output
+        )
+    }
+}
+
+// Runs commands for the droid, adding aliases, macros, navigation and saved games on top of the game's own commands
+struct Console {
+    droid: Droid,
+    map: ShipMap,
+    macros: HashMap<String, String>,
+    saves: HashMap<String, Droid>,
+}
+
+impl Console {
+    // Returns the console along with the game's opening output
+    fn new(droid: Droid) -> (Self, String) {
+        let mut console = Self {
+            droid,
+            map: ShipMap::default(),
+            macros: HashMap::new(),
+            saves: HashMap::new(),
+        };
+        let output = console.droid.read_output();
+        console.droid.update(&EventParser::parse(&output));
+        (console, output)
+    }
+
+    fn execute_line(&mut self, line: &str) -> String {
+        self.execute_line_nested(line, 0)
+    }
+
+    fn execute_line_nested(&mut self, line: &str, depth: usize) -> String {
+        let mut output = String::new();
+        for piece in split_line(line) {
+            match self.macros.get(piece).cloned() {
+                Some(_) if depth >= MACRO_DEPTH_LIMIT => {
+                    output.push_str("Macros are nested too deeply.\n")
+                }
+                Some(body) => output.push_str(&self.execute_line_nested(&body, depth + 1)),
+                None => output.push_str(&self.execute(Command::from_string(piece))),
+            }
+        }
+        output
+    }
+
+    fn execute(&mut self, command: Command) -> String {
+        match command {
+            Command::Goto(room) => {
+                let start = self.droid.current_room();
+                match self.map.route(&start, &room) {
+                    Some(path) => path.into_iter().map(|c| self.execute(c)).collect(),
+                    None => format!("I don't know how to get to {}.\n", room),
+                }
+            }
+            Command::Save(name) => {
+                self.saves.insert(name.clone(), self.droid.clone());
+                format!("Saved game {}.\n", name)
+            }
+            Command::Load(name) => match self.saves.get(&name) {
+                Some(droid) => {
+                    self.droid = droid.clone();
+                    format!("Loaded game {}.\n", name)
+                }
+                None => format!("No saved game {}.\n", name),
+            },
+            Command::Define(name, body) => {
+                self.macros.insert(name.clone(), body);
+                format!("Defined {}.\n", name)
+            }
+            Command::Expect(_) => String::new(), // Only meaningful in scripts
+            Command::Unknown => String::from("Unknown command; try again:\n"),
+            command => self.send(command),
+        }
+    }
+
+    fn send(&mut self, command: Command) -> String {
+        let from = self.droid.current_room();
+        let door = command.clone();
+
+        self.droid.give_command(command);
+        let output = self.droid.read_output();
+        let events = EventParser::parse(&output);
+        self.droid.update(&events);
+
+        if door.is_movement() == true && events.iter().all(|e| !matches!(e, Event::Ejected { .. }))
+        {
+            if let Some(room) = last_room(&events) {
+                self.map.link(&from, &door, &room.name);
+            }
+        }
+        output
+    }
+
+    // Plays the game from stdin, with all of the console's extra commands available
+    fn run_interactive(&mut self, opening: &str) {
+        print!("{}", opening);
+        let stdin = io::stdin();
+        while self.droid.program.halted == false {
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            print!("{}", self.execute_line(&line));
+        }
+    }
+
+    // Runs a session script, one line of commands at a time. An `expect` line checks the output of the line before it.
+    fn run_script(&mut self, script: &str) -> Result<String, SessionError> {
+        let mut transcript = String::new();
+        let mut last_output = String::new();
+        for (i, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() == true || line.starts_with('#') == true {
+                continue;
+            }
+
+            if let Command::Expect(expected) = Command::from_string(line) {
+                if last_output.contains(&expected) == false {
+                    return Err(SessionError {
+                        line: i + 1,
+                        expected,
+                        output: last_output,
+                    });
+                }
+                continue;
+            }
+
+            last_output = self.execute_line(line);
+            transcript.push_str(&last_output);
+        }
+        Ok(transcript)
+    }
+
+    fn run_script_file(&mut self, path: &str) -> Result<String, SessionError> {
+        let script = fs::read_to_string(path).expect("Something went wrong reading the file");
+        self.run_script(&script)
+    }
+}
+
+struct Solver {
+    droid: Droid,
+    rooms: HashMap<String, Room>,
+    map: ShipMap,
+    checkpoint: Option<(String, Command)>, // The room before the pressure-sensitive floor, and the door to it
+}
+
+impl Solver {
+    fn new(droid: Droid) -> Self {
+        Self {
+            droid,
+            rooms: HashMap::new(),
+            map: ShipMap::default(),
+            checkpoint: None,
+        }
+    }
+
+    fn solve(&mut self) -> Option<String> {
+        let events = self.droid.read_events();
+        let start = last_room(&events)?;
+        self.explore(start);
+
+        let (checkpoint, door) = self.checkpoint.clone()?;
+        for command in self.map.route(&self.droid.current_room(), &checkpoint)? {
+            self.droid.send(command);
+        }
+        self.try_items(door);
+
+        self.droid.password.clone()
+    }
+
+    fn explore(&mut self, room: Room) {
+        self.rooms.insert(room.name.clone(), room.clone());
+
+        for item in &room.items {
+            self.take_if_safe(item, &room);
+        }
+
+        for door in &room.doors {
+            let link = (room.name.clone(), door.clone());
+            if self.map.contains(&room.name, door) == true
+                || self.checkpoint.as_ref() == Some(&link)
+            {
+                continue;
+            }
+
+            let events = self.droid.send(door.clone());
+            if events.iter().any(|e| matches!(e, Event::Ejected { .. })) {
+                // Bounced straight back, so this door leads to the pressure-sensitive floor
+                self.checkpoint = Some(link);
+                continue;
+            }
+            let next = match last_room(&events) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            self.map.link(&room.name, door, &next.name);
+            if self.rooms.contains_key(&next.name) == false {
+                self.explore(next);
+            }
+
+            self.droid.send(door.opposite());
+        }
+    }
+
+    // Some items end the game or trap the droid, so take a snapshot first and go back to it if anything goes wrong
+    fn take_if_safe(&mut self, item: &str, room: &Room) {
+        let snapshot = self.droid.clone();
+        self.droid.send(Command::Take(item.to_owned()));
+
+        let mut trapped = self.droid.game_over;
+        if trapped == false {
+            if let Some(door) = room.doors.first() {
+                let mut probe = self.droid.clone();
+                trapped = probe.send(door.clone()).contains(&Event::Stuck);
+            }
+        }
+
+        if trapped == true {
+            self.droid = snapshot;
+        }
+    }
+
+    // Try every combination of items on the pressure-sensitive floor
+    fn try_items(&mut self, door: Command) {
+        let snapshot = self.droid.clone();
+        let items = snapshot.inventory.clone();
+        for mask in 0..(1u32 << items.len()) {
+            self.droid = snapshot.clone();
+            for (i, item) in items.iter().enumerate() {
+                if mask & (1 << i) == 0 {
+                    self.droid.send(Command::Drop(item.clone()));
+                }
+            }
+
+            self.droid.send(door.clone());
+            if self.droid.password.is_some() {
+                return;
             }
         }
     }
@@ -412,38 +963,294 @@ pub fn solve(input: &str) -> String {
         .map(|s| s.parse::<i64>().unwrap())
         .collect();
     let program = Program::new(&code, &[]);
-    let mut droid = Droid::new(program);
-
-    // I manually explored and experimented with the items. More fun that way :-)
-    let commands = vec![
-        Command::North,
-        Command::North,
-        Command::Take(String::from("monolith")),
-        Command::North,
-        Command::Take(String::from("hypercube")),
-        Command::South,
-        Command::South,
-        Command::East,
-        Command::East,
-        Command::Take(String::from("easter egg")),
-        Command::East,
-        Command::South,
-        Command::Take(String::from("ornament")),
-        Command::West,
-        Command::South,
-        Command::West,
-    ];
-    droid.commands.extend(commands);
-
-    droid.run();
-    String::from("See output above")
+    let droid = Droid::new(program);
+
+    let mut solver = Solver::new(droid);
+    let password = solver.solve().expect("Could not find the password");
+    println!("Password: {}", password);
+    password
+}
+
+// Play the game by hand, with the console's extra commands. Run with:
+// cargo run --release --example day25_console
+pub fn play(input: &str) {
+    let code: Vec<i64> = input
+        .trim()
+        .split(',')
+        .map(|s| s.parse::<i64>().unwrap())
+        .collect();
+    let (mut console, opening) = Console::new(Droid::new(Program::new(&code, &[])));
+    console.run_interactive(&opening);
 }
 
 #[cfg(test)]
 mod test {
-    #[allow(unused_imports)] // No tests for this module
     use super::*;
+    use std::fs;
+
+    fn room(name: &str, description: &str, doors: &[Command], items: &[&str]) -> Event {
+        Event::RoomEntered(Room {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            doors: doors.to_vec(),
+            items: items.iter().map(|&i| i.to_owned()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let transcript = "
+
+
+== Observatory ==
+There are a few telescopes; they're all bolted down, though.
+
+Doors here lead:
+- north
+- south
+- west
+
+Items here:
+- monolith
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [room(
+                "Observatory",
+                "There are a few telescopes; they're all bolted down, though.",
+                &[Command::North, Command::South, Command::West],
+                &["monolith"]
+            )]
+        );
+
+        let transcript = "
+You take the monolith.
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [Event::ItemTaken("monolith".to_owned())]
+        );
+
+        let transcript = "
+You drop the monolith.
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [Event::ItemDropped("monolith".to_owned())]
+        );
+
+        let transcript = "
+Items in your inventory:
+- monolith
+- easter egg
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [Event::InventoryListed(vec![
+                "monolith".to_owned(),
+                "easter egg".to_owned()
+            ])]
+        );
+
+        let transcript = "
+You aren't carrying any items.
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [Event::InventoryListed(Vec::new())]
+        );
+    }
+
+    #[test]
+    fn test_parse_events_checkpoint() {
+        let transcript = "
+
+
+== Pressure-Sensitive Floor ==
+Analyzing...
+
+Doors here lead:
+- east
+
+A loud, robotic voice says \"Alert! Droids on this ship are heavier than the detected value!\" and you are ejected back to the checkpoint.
+
+
+
+== Security Checkpoint ==
+In the next room, a pressure-sensitive floor will verify your identity.
+
+Doors here lead:
+- north
+- west
+
+Command?
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [
+                room(
+                    "Pressure-Sensitive Floor",
+                    "Analyzing...",
+                    &[Command::East],
+                    &[]
+                ),
+                Event::Ejected {
+                    heavier_or_lighter: Weight::Heavier
+                },
+                room(
+                    "Security Checkpoint",
+                    "In the next room, a pressure-sensitive floor will verify your identity.",
+                    &[Command::North, Command::West],
+                    &[]
+                ),
+            ]
+        );
+
+        let transcript = "
+
+
+== Pressure-Sensitive Floor ==
+Analyzing...
+
+Doors here lead:
+- east
+
+A loud, robotic voice says \"Analysis complete! You may proceed.\" and you enter the cockpit.
+Santa notices your small droid, looks puzzled for a moment, realizes what has happened, and radios your ship directly.
+\"Oh, hello! You should be able to get in by typing 1073815584 on the keypad at the main airlock.\"
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [
+                room(
+                    "Pressure-Sensitive Floor",
+                    "Analyzing...",
+                    &[Command::East],
+                    &[]
+                ),
+                Event::Password("1073815584".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_events_game_over() {
+        let transcript = "
+You take the escape pod.
+
+You're launched into space! Bye!
+";
+        assert_eq!(
+            EventParser::parse(transcript),
+            [
+                Event::ItemTaken("escape pod".to_owned()),
+                Event::GameOver {
+                    reason: "You're launched into space! Bye!".to_owned()
+                },
+            ]
+        );
+
+        let transcript = "
+The giant electromagnet is stuck to you.  You can't move!!
+
+Command?
+";
+        assert_eq!(EventParser::parse(transcript), [Event::Stuck]);
+    }
+
+    #[test]
+    fn test_solve() {
+        let input = fs::read_to_string("input/2019/day25.txt")
+            .expect("Something went wrong reading the file");
+        assert_eq!(solve(&input), "1073815584");
+    }
 
     #[test]
-    fn test_() {}
+    fn test_command_from_string() {
+        assert_eq!(Command::from_string("n"), Command::North);
+        assert_eq!(Command::from_string("west\n"), Command::West);
+        assert_eq!(Command::from_string("i"), Command::List);
+        assert_eq!(
+            Command::from_string("take  easter egg"),
+            Command::Take("easter egg".to_owned())
+        );
+        assert_eq!(
+            Command::from_string("goto Security Checkpoint"),
+            Command::Goto("Security Checkpoint".to_owned())
+        );
+        assert_eq!(
+            Command::from_string("define back = s; s"),
+            Command::Define("back".to_owned(), "s; s".to_owned())
+        );
+        assert_eq!(Command::from_string("dance"), Command::Unknown);
+
+        assert_eq!(
+            split_line("n; n;take monolith "),
+            ["n", "n", "take monolith"]
+        );
+        assert_eq!(split_line("define back = s; s"), ["define back = s; s"]);
+    }
+
+    fn console() -> Console {
+        let input = fs::read_to_string("input/2019/day25.txt")
+            .expect("Something went wrong reading the file");
+        let code: Vec<i64> = input
+            .trim()
+            .split(',')
+            .map(|s| s.parse::<i64>().unwrap())
+            .collect();
+        let (console, output) = Console::new(Droid::new(Program::new(&code, &[])));
+        assert!(output.contains("== Hull Breach =="));
+        console
+    }
+
+    #[test]
+    fn test_console_script() {
+        let mut console = console();
+        let transcript = console
+            .run_script_file("input/2019/day25_session.txt")
+            .unwrap();
+        assert!(transcript.contains("== Corridor =="));
+        assert_eq!(console.droid.current_room(), "Observatory");
+        assert_eq!(console.droid.inventory, ["monolith"]);
+    }
+
+    #[test]
+    fn test_console_script_error() {
+        let script = "
+            n
+            expect == Corridor ==
+            s
+            expect == Corridor ==
+        ";
+        let error = console().run_script(script).unwrap_err();
+        assert_eq!(error.line, 5);
+        assert_eq!(error.expected, "== Corridor ==");
+        assert!(error.output.contains("== Hull Breach =="));
+    }
+
+    #[test]
+    fn test_console_macros() {
+        let mut console = console();
+        console.execute_line("define loop = loop");
+        assert!(console
+            .execute_line("loop")
+            .contains("Macros are nested too deeply."));
+
+        console.execute_line("define corridor = n");
+        console.execute_line("define observatory = corridor; corridor");
+        assert!(console
+            .execute_line("observatory")
+            .contains("== Observatory =="));
+    }
 }
@@ -58,4 +58,7 @@ mod day_24_part1;
 mod day_24_part2;
 mod day_25_part1;
 
+// Day 25 can also be played by hand, see examples/day25_console.rs
+pub use day_25_part1::play as play_day25;
+
 aoc_lib! { year = 2019 }